@@ -0,0 +1,53 @@
+//! Measures how long a model takes per tick with different numbers of threads.
+//!
+//! Usage: `cargo run --release --no-default-features --example tick_timing -- <config file> [ticks] [max threads]`
+//!
+//! Runs the same seeded model for the given number of ticks (default: 20) with 1, 2, 4, ... threads,
+//! up to the given maximum (default: the number of CPU cores), and prints the time per tick and the speedup.
+use onderzoeksmethoden::models::params::params_from_file_with_seed;
+use onderzoeksmethoden::models::{create_model, Model};
+use std::time::Instant;
+
+const SEED: u64 = 42;
+
+fn main() {
+    let arguments: Vec<String> = std::env::args().collect();
+    if arguments.len() < 2 {
+        println!(
+            "Usage: {} <config file> [ticks] [max threads]",
+            arguments[0]
+        );
+        std::process::exit(1);
+    }
+    let config_path = &arguments[1];
+    let ticks: usize = arguments.get(2).map_or(20, |ticks| ticks.parse().unwrap());
+    let max_threads: usize = arguments.get(3).map_or_else(
+        || std::thread::available_parallelism().map_or(1, |n| n.get()),
+        |threads| threads.parse().unwrap(),
+    );
+
+    let mut single_threaded_seconds = None;
+    let mut threads = 1;
+    while threads <= max_threads {
+        let mut params =
+            params_from_file_with_seed(config_path, Some(SEED)).expect("Failed to load parameters");
+        params.threads = Some(threads);
+        let mut model: Box<dyn Model> = create_model(params).expect("Failed to create model");
+        model.populate().expect("Failed to populate model");
+
+        let start = Instant::now();
+        for _ in 0..ticks {
+            model.tick();
+        }
+        let seconds = start.elapsed().as_secs_f64();
+        let single_threaded_seconds = *single_threaded_seconds.get_or_insert(seconds);
+
+        println!(
+            "{} threads: {:.1} ms per tick, {:.2}x speedup",
+            threads,
+            seconds * 1000.0 / ticks as f64,
+            single_threaded_seconds / seconds
+        );
+        threads *= 2;
+    }
+}
@@ -0,0 +1,48 @@
+use crate::models::{create_model, Grid, Model, ModelParams};
+use crate::util::PRng;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+
+/// Full state of a running simulation. Resuming from a checkpoint continues exactly like the original run would have.
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    params: ModelParams,
+    ticks_elapsed: usize,
+    grid: Grid,
+    rng: PRng,
+}
+
+/// Saves the state of the model to a JSON file.
+pub fn save_checkpoint(
+    file_path: &str,
+    model: &dyn Model,
+    ticks_elapsed: usize,
+) -> Result<(), Box<dyn Error>> {
+    // Record the seed that was actually used, so the resumed model reports the same seed
+    let mut params = model.get_params().clone();
+    params.random_seed = Some(model.get_seed());
+
+    let checkpoint = Checkpoint {
+        params,
+        ticks_elapsed,
+        grid: model.get_grid().clone(),
+        rng: model.get_rng().clone(),
+    };
+
+    fs::write(file_path, serde_json::to_string(&checkpoint)?)?;
+
+    Ok(())
+}
+
+/// Loads a model from a checkpoint file. Returns the model and the number of ticks elapsed at the time of saving.
+/// The parameters contain the images, terrain map and time series that were read when they were loaded,
+/// so no other files are read and the checkpoint does not change meaning if those files move or change.
+pub fn load_checkpoint(file_path: &str) -> Result<(Box<dyn Model>, usize), Box<dyn Error>> {
+    let checkpoint: Checkpoint = serde_json::from_str(&fs::read_to_string(file_path)?)?;
+
+    let mut model = create_model(checkpoint.params)?;
+    model.restore(checkpoint.grid, checkpoint.rng, checkpoint.ticks_elapsed);
+
+    Ok((model, checkpoint.ticks_elapsed))
+}
@@ -0,0 +1,134 @@
+//! Conversions between the positions of cells in the grid and pixels on the canvas.
+//! Kept apart from the SDL2 drawing code, so that they can be used and tested without the `gui` feature.
+
+use crate::models::params::Topology;
+use crate::util::Size;
+
+/// Returns the vertical distance in pixels between two rows of cells.
+/// Rows of hexagonal cells interlock, and are therefore closer together than rows of square cells.
+pub fn row_height(topology: Topology, scale: u32) -> u32 {
+    match topology {
+        Topology::Square => scale,
+        Topology::Hexagonal => ((scale as f32 * 3f32.sqrt() / 2.0).round() as u32).max(1),
+    }
+}
+
+/// Returns the point on the canvas at which the top left corner of the cell at (x, y) is drawn,
+/// when the cell at the midpoint is drawn in the middle of the canvas with the given scale (pixels per cell).
+pub fn cell_to_canvas(
+    (x, y): (i32, i32),
+    canvas_size: Size,
+    midpoint: (i32, i32),
+    scale: u32,
+    topology: Topology,
+) -> (i32, i32) {
+    let mut draw_x = (canvas_size.w / 2) as i32 + (x - midpoint.0) * scale as i32;
+    let draw_y = (canvas_size.h / 2) as i32 + (y - midpoint.1) * row_height(topology, scale) as i32;
+
+    if topology == Topology::Hexagonal && y % 2 != 0 {
+        // Odd rows are shifted half a cell to the right
+        draw_x += scale as i32 / 2;
+    }
+
+    (draw_x, draw_y)
+}
+
+/// Returns the position of the cell that is drawn at the given point on the canvas, the inverse of `cell_to_canvas`.
+/// The position may lie outside of the grid.
+pub fn canvas_to_cell(
+    (canvas_x, canvas_y): (i32, i32),
+    canvas_size: Size,
+    midpoint: (i32, i32),
+    scale: u32,
+    topology: Topology,
+) -> (i32, i32) {
+    let y = (canvas_y - (canvas_size.h / 2) as i32).div_euclid(row_height(topology, scale) as i32)
+        + midpoint.1;
+
+    let mut offset_x = canvas_x - (canvas_size.w / 2) as i32;
+    if topology == Topology::Hexagonal && y % 2 != 0 {
+        // Odd rows are shifted half a cell to the right
+        offset_x -= scale as i32 / 2;
+    }
+    let x = offset_x.div_euclid(scale as i32) + midpoint.0;
+
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_pixel_of_a_cell_maps_back_to_it() {
+        let canvas_size = Size::new(640, 480);
+
+        for &topology in &[Topology::Square, Topology::Hexagonal] {
+            for &scale in &[1, 2, 3, 8, 13, 64] {
+                for &midpoint in &[(0, 0), (20, 15), (7, 3), (-4, 9)] {
+                    for y in -6..6 {
+                        for x in -6..6 {
+                            let cell = (midpoint.0 + x, midpoint.1 + y);
+                            let (draw_x, draw_y) =
+                                cell_to_canvas(cell, canvas_size, midpoint, scale, topology);
+
+                            // The cell covers a rectangle of scale by row height pixels from its top left corner
+                            for dy in 0..row_height(topology, scale) as i32 {
+                                for dx in 0..scale as i32 {
+                                    let point = (draw_x + dx, draw_y + dy);
+                                    assert_eq!(
+                                        canvas_to_cell(
+                                            point,
+                                            canvas_size,
+                                            midpoint,
+                                            scale,
+                                            topology
+                                        ),
+                                        cell
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn midpoint_is_drawn_in_the_middle() {
+        let canvas_size = Size::new(101, 60);
+
+        for &topology in &[Topology::Square, Topology::Hexagonal] {
+            assert_eq!(
+                cell_to_canvas((10, 4), canvas_size, (10, 4), 8, topology),
+                (50, 30)
+            );
+            assert_eq!(
+                canvas_to_cell((50, 30), canvas_size, (10, 4), 8, topology),
+                (10, 4)
+            );
+        }
+    }
+
+    #[test]
+    fn odd_hex_rows_are_shifted_half_a_cell() {
+        let canvas_size = Size::new(100, 100);
+
+        let (even_x, _) = cell_to_canvas((3, 2), canvas_size, (0, 0), 10, Topology::Hexagonal);
+        let (odd_x, _) = cell_to_canvas((3, 3), canvas_size, (0, 0), 10, Topology::Hexagonal);
+        assert_eq!(odd_x - even_x, 5);
+
+        // The pixel just left of a cell on an odd row belongs to the cell before it
+        assert_eq!(
+            canvas_to_cell(
+                (odd_x - 1, 50 + 3 * 9),
+                canvas_size,
+                (0, 0),
+                10,
+                Topology::Hexagonal
+            ),
+            (2, 3)
+        );
+    }
+}
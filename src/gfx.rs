@@ -1,9 +1,13 @@
-use crate::models::{Cell, Model};
+mod font;
+
+use crate::coords::{canvas_to_cell, cell_to_canvas, row_height};
+use crate::models::{params::ModelParams, params::Topology, Cell, Model};
+use crate::stats::{population_counts, PopulationHistory};
 use crate::util::Size;
 use sdl2::{
     pixels::Color,
     rect::{Point, Rect},
-    render::Canvas,
+    render::{BlendMode, Canvas},
     video::Window,
 };
 use std::collections::HashMap;
@@ -30,22 +34,185 @@ impl View {
             self.scale /= 2;
         }
     }
+
+    /// Moves the view by the given number of cells, keeping the midpoint within the grid.
+    pub fn pan(&mut self, dx: i32, dy: i32, grid_size: Size) {
+        self.midpoint = Point::new(
+            (self.midpoint.x + dx).clamp(0, grid_size.w as i32),
+            (self.midpoint.y + dy).clamp(0, grid_size.h as i32),
+        );
+    }
+
+    /// Moves the view along with the mouse, which has moved the given number of pixels since the view was at the given midpoint.
+    pub fn drag(
+        &mut self,
+        start_midpoint: Point,
+        dx: i32,
+        dy: i32,
+        grid_size: Size,
+        topology: Topology,
+    ) {
+        self.midpoint = start_midpoint;
+        self.pan(
+            -dx / self.scale as i32,
+            -dy / row_height(topology, self.scale) as i32,
+            grid_size,
+        );
+    }
+
+    /// Increases or decreases the scale, while keeping the cell at the given point on the canvas in place.
+    pub fn zoom_at(
+        &mut self,
+        canvas_point: Point,
+        canvas_size: Size,
+        grid_size: Size,
+        topology: Topology,
+        zoom_in: bool,
+    ) {
+        let cell = canvas_to_model_coord(canvas_point, canvas_size, self, topology);
+        if zoom_in {
+            self.increase_scale();
+        } else {
+            self.decrease_scale();
+        }
+        let new_cell = canvas_to_model_coord(canvas_point, canvas_size, self, topology);
+
+        self.pan(cell.x - new_cell.x, cell.y - new_cell.y, grid_size);
+    }
+
+    /// Centers the grid and picks the largest scale at which the whole grid fits on the canvas.
+    pub fn fit(&mut self, canvas_size: Size, grid_size: Size, topology: Topology) {
+        let fits = |scale: u32| match topology {
+            Topology::Square => {
+                grid_size.w * scale <= canvas_size.w && grid_size.h * scale <= canvas_size.h
+            }
+            // Odd rows are shifted half a cell to the right
+            Topology::Hexagonal => {
+                grid_size.w * scale + scale / 2 <= canvas_size.w
+                    && grid_size.h * row_height(topology, scale) <= canvas_size.h
+            }
+        };
+
+        self.scale = (1..=(canvas_size.w / grid_size.w).max(1))
+            .rev()
+            .find(|scale| fits(*scale))
+            .unwrap_or(1);
+        self.midpoint = Point::new(grid_size.w as i32 / 2, grid_size.h as i32 / 2);
+    }
 }
 
 const BACKGROUND_COLOR: Color = Color::RGBA(100, 100, 100, 255);
 const CELL_EMPTY_COLOR: Color = Color::RGBA(220, 220, 220, 255);
 const CELL_ANIMAL_DEFAULT_COLOR: Color = Color::RGBA(200, 90, 10, 255);
+const CELL_OBSTACLE_DEFAULT_COLOR: Color = Color::RGBA(60, 60, 60, 255);
 const GRID_DIVIDER_COLOR: Color = Color::RGBA(140, 140, 140, 255);
+const OVERLAY_BACKGROUND_COLOR: Color = Color::RGBA(0, 0, 0, 170);
+const TEXT_COLOR: Color = Color::RGBA(240, 240, 240, 255);
+const TEXT_SCALE: u32 = 2; // Pixels per font pixel
+const TEXT_ADVANCE: i32 = ((font::GLYPH_WIDTH + 1) * TEXT_SCALE) as i32; // Horizontal distance in pixels between characters
+const TEXT_LINE_HEIGHT: i32 = ((font::GLYPH_HEIGHT + 4) * TEXT_SCALE) as i32;
+const HUD_PADDING: i32 = 8; // Distance in pixels between the edges of a panel and its text
+const TOOLTIP_OFFSET: i32 = 16; // Distance in pixels between the mouse cursor and the tooltip
+const CHART_SIZE: Size = Size::new(360, 180);
+const CHART_MARGIN: i32 = 10; // Distance in pixels between the chart and the edges of the canvas
+const CHART_PADDING: i32 = 8; // Distance in pixels between the chart's edges and the plot
 const MIN_SCALE_FOR_DRAWING_GRID: u32 = 8;
+// Minimum scale at which hexagonal cells are drawn as hexagons instead of shifted squares.
+const MIN_SCALE_FOR_DRAWING_HEXAGONS: u32 = 4;
 
-fn model_to_canvas_coord(model_coord: Point, canvas_size: Size, view: &View) -> Point {
-    let draw_x = (canvas_size.w / 2) as i32 + (model_coord.x - view.midpoint.x) * view.scale as i32;
-    let draw_y = (canvas_size.h / 2) as i32 + (model_coord.y - view.midpoint.y) * view.scale as i32;
+/// Returns the point on the canvas at which the top left corner of the cell at the given position is drawn.
+fn model_to_canvas_coord(
+    model_coord: Point,
+    canvas_size: Size,
+    view: &View,
+    topology: Topology,
+) -> Point {
+    let (draw_x, draw_y) = cell_to_canvas(
+        (model_coord.x, model_coord.y),
+        canvas_size,
+        (view.midpoint.x, view.midpoint.y),
+        view.scale,
+        topology,
+    );
 
     Point::new(draw_x, draw_y)
 }
 
-pub fn draw_model(canvas: &mut Canvas<Window>, model: &Box<dyn Model>, view: &View) {
+/// Returns the position of the cell that is drawn at the given point on the canvas, the inverse of `model_to_canvas_coord`.
+/// The position may lie outside of the grid.
+pub fn canvas_to_model_coord(
+    canvas_point: Point,
+    canvas_size: Size,
+    view: &View,
+    topology: Topology,
+) -> Point {
+    let (model_x, model_y) = canvas_to_cell(
+        (canvas_point.x, canvas_point.y),
+        canvas_size,
+        (view.midpoint.x, view.midpoint.y),
+        view.scale,
+        topology,
+    );
+
+    Point::new(model_x, model_y)
+}
+
+/// Draws a pointy-top hexagon that fills the given cell position, as a series of horizontal lines.
+fn draw_hexagon(canvas: &mut Canvas<Window>, draw_point: Point, scale: u32, row_height: u32) {
+    // The hexagon's pointy top and bottom extend a third of the row height into the neighboring rows
+    let tip_height = row_height as f32 / 3.0;
+    let total_height = (tip_height * 4.0).round() as i32;
+    let top_y = draw_point.y - tip_height.round() as i32;
+    let center_x = draw_point.x as f32 + scale as f32 / 2.0;
+    // Leave a gap between the hexagons when the grid dividers are drawn
+    let gap = if scale >= MIN_SCALE_FOR_DRAWING_GRID {
+        1.0
+    } else {
+        0.0
+    };
+
+    for line in 0..total_height {
+        let line_center = line as f32 + 0.5;
+        let distance_to_tip = line_center.min(total_height as f32 - line_center);
+        let half_width = scale as f32 / 2.0 * (distance_to_tip / tip_height).min(1.0) - gap / 2.0;
+
+        if half_width > 0.0 {
+            let start_x = (center_x - half_width).round() as i32;
+            let end_x = (center_x + half_width).round() as i32;
+            if end_x > start_x {
+                canvas
+                    .fill_rect(Rect::new(
+                        start_x,
+                        top_y + line,
+                        (end_x - start_x) as u32,
+                        1,
+                    ))
+                    .unwrap();
+            }
+        }
+    }
+}
+
+/// Converts a color in hex format (e.g. FF0000) to an SDL color.
+fn hex_to_color(hex_color: &str) -> Color {
+    let hex_color = u32::from_str_radix(hex_color, 16).unwrap();
+
+    Color::RGB(
+        (hex_color >> 16) as u8,
+        (hex_color >> 8) as u8,
+        hex_color as u8,
+    )
+}
+
+/// Returns the configured color of the given specie.
+fn specie_color(params: &ModelParams, specie_id: u32) -> Color {
+    match &params.get_specie_by_id(specie_id).color {
+        Some(specie_color) => hex_to_color(specie_color),
+        None => CELL_ANIMAL_DEFAULT_COLOR,
+    }
+}
+
+pub fn draw_model(canvas: &mut Canvas<Window>, model: &dyn Model, view: &View) {
     canvas.set_draw_color(BACKGROUND_COLOR);
     canvas.clear();
 
@@ -53,44 +220,45 @@ pub fn draw_model(canvas: &mut Canvas<Window>, model: &Box<dyn Model>, view: &Vi
 
     let grid = model.get_grid();
     let grid_size = grid.get_size();
+    let topology = grid.get_topology();
+    let row_height = row_height(topology, view.scale);
     let params = model.get_params();
-    let specie_ids = params.specie_ids();
     let (canvas_width, canvas_height) = canvas.output_size().unwrap();
     let canvas_size = Size::new(canvas_width, canvas_height);
 
     let mut color_cache = HashMap::new();
+    let mut habitat_color_cache = HashMap::new();
 
     let mut prev_color = Color::RGBA(0, 0, 0, 0);
     for x in 0..grid_size.w {
         for y in 0..grid_size.h {
             let draw_point =
-                model_to_canvas_coord(Point::new(x as i32, y as i32), canvas_size, view);
-            let draw_rect = Rect::new(draw_point.x, draw_point.y, view.scale, view.scale);
+                model_to_canvas_coord(Point::new(x as i32, y as i32), canvas_size, view, topology);
+
+            // Skip cells that are not visible
+            if draw_point.x + (view.scale as i32) < 0
+                || draw_point.x >= canvas_size.w as i32
+                || draw_point.y + (view.scale as i32) < 0
+                || draw_point.y - (row_height as i32) >= canvas_size.h as i32
+            {
+                continue;
+            }
 
             let color = match grid.get_cell_at(x, y) {
-                Cell::Empty => CELL_EMPTY_COLOR,
-                Cell::Animal(specie_id) => {
-                    if !color_cache.contains_key(specie_id) {
-                        let color_str =
-                            &params.species[specie_ids.get_by_right(specie_id).unwrap()].color;
-                        let color = match color_str {
-                            Some(specie_color) => {
-                                let hex_color = u32::from_str_radix(specie_color, 16).unwrap();
-
-                                Color::RGB(
-                                    (hex_color >> 16) as u8,
-                                    (hex_color >> 8) as u8,
-                                    hex_color as u8,
-                                )
-                            }
-                            None => CELL_ANIMAL_DEFAULT_COLOR,
-                        };
-
-                        color_cache.insert(specie_id, color);
-                    }
-
-                    color_cache[specie_id]
-                }
+                Cell::Empty => match grid.get_habitat_id_at(x, y) {
+                    0 => CELL_EMPTY_COLOR,
+                    habitat_id => *habitat_color_cache.entry(habitat_id).or_insert_with(|| {
+                        let habitat_params = params.get_habitat_by_id(habitat_id);
+                        match &habitat_params.color {
+                            Some(habitat_color) => hex_to_color(habitat_color),
+                            None if habitat_params.obstacle => CELL_OBSTACLE_DEFAULT_COLOR,
+                            None => CELL_EMPTY_COLOR,
+                        }
+                    }),
+                },
+                Cell::Animal(specie_id, _) => *color_cache
+                    .entry(*specie_id)
+                    .or_insert_with(|| specie_color(params, *specie_id)),
             };
 
             if color != prev_color {
@@ -98,22 +266,35 @@ pub fn draw_model(canvas: &mut Canvas<Window>, model: &Box<dyn Model>, view: &Vi
                 prev_color = color;
             }
 
-            canvas.fill_rect(draw_rect).unwrap();
+            if topology == Topology::Hexagonal && view.scale >= MIN_SCALE_FOR_DRAWING_HEXAGONS {
+                draw_hexagon(canvas, draw_point, view.scale, row_height);
+            } else {
+                let draw_rect = Rect::new(draw_point.x, draw_point.y, view.scale, row_height);
+                canvas.fill_rect(draw_rect).unwrap();
+            }
         }
     }
 
-    draw_grid(canvas, canvas_size, grid_size, view);
+    if topology == Topology::Square {
+        draw_grid(canvas, canvas_size, grid_size, view);
+    }
 }
 
+/// Draws the dividers between square cells. Hexagonal cells are drawn with a gap in between instead.
 pub fn draw_grid(canvas: &mut Canvas<Window>, canvas_size: Size, grid_size: Size, view: &View) {
     if view.scale >= MIN_SCALE_FOR_DRAWING_GRID {
         canvas.set_draw_color(GRID_DIVIDER_COLOR);
 
         // Draw horizontal lines
         for y in 0..grid_size.h {
-            let start_point = model_to_canvas_coord(Point::new(0, y as i32), canvas_size, view);
-            let end_point =
-                model_to_canvas_coord(Point::new(grid_size.w as i32, y as i32), canvas_size, view);
+            let start_point =
+                model_to_canvas_coord(Point::new(0, y as i32), canvas_size, view, Topology::Square);
+            let end_point = model_to_canvas_coord(
+                Point::new(grid_size.w as i32, y as i32),
+                canvas_size,
+                view,
+                Topology::Square,
+            );
 
             let draw_rect = Rect::new(
                 start_point.x,
@@ -127,9 +308,14 @@ pub fn draw_grid(canvas: &mut Canvas<Window>, canvas_size: Size, grid_size: Size
 
         // Draw vertical lines
         for x in 0..grid_size.w {
-            let start_point = model_to_canvas_coord(Point::new(x as i32, 0), canvas_size, view);
-            let end_point =
-                model_to_canvas_coord(Point::new(x as i32, grid_size.h as i32), canvas_size, view);
+            let start_point =
+                model_to_canvas_coord(Point::new(x as i32, 0), canvas_size, view, Topology::Square);
+            let end_point = model_to_canvas_coord(
+                Point::new(x as i32, grid_size.h as i32),
+                canvas_size,
+                view,
+                Topology::Square,
+            );
 
             let draw_rect = Rect::new(
                 start_point.x,
@@ -142,3 +328,255 @@ pub fn draw_grid(canvas: &mut Canvas<Window>, canvas_size: Size, grid_size: Size
         }
     }
 }
+
+/// Draws a chart of the population of every specie over the last ticks in the bottom right corner of the canvas.
+/// The vertical axis runs from zero to the largest population in the history.
+pub fn draw_population_chart(
+    canvas: &mut Canvas<Window>,
+    model: &dyn Model,
+    history: &PopulationHistory,
+) {
+    let (canvas_width, canvas_height) = canvas.output_size().unwrap();
+    let chart_rect = Rect::new(
+        canvas_width as i32 - CHART_SIZE.w as i32 - CHART_MARGIN,
+        canvas_height as i32 - CHART_SIZE.h as i32 - CHART_MARGIN,
+        CHART_SIZE.w,
+        CHART_SIZE.h,
+    );
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(OVERLAY_BACKGROUND_COLOR);
+    canvas.fill_rect(chart_rect).unwrap();
+    canvas.set_blend_mode(BlendMode::None);
+
+    let counts = history.get_counts();
+    let max_count = counts
+        .iter()
+        .flat_map(|tick_counts| tick_counts.iter())
+        .cloned()
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let plot_width = CHART_SIZE.w as i32 - 2 * CHART_PADDING;
+    let plot_height = CHART_SIZE.h as i32 - 2 * CHART_PADDING;
+    let plot_bottom = chart_rect.bottom() - CHART_PADDING;
+    // The newest tick is at the right edge, the history fills the plot from the right
+    let tick_x = |tick_index: usize| {
+        chart_rect.right()
+            - CHART_PADDING
+            - ((counts.len() - 1 - tick_index) as i64 * plot_width as i64
+                / (history.get_length().max(2) - 1) as i64) as i32
+    };
+
+    for specie_index in 0..model.get_params().species.len() {
+        let points: Vec<Point> = counts
+            .iter()
+            .enumerate()
+            .map(|(tick_index, tick_counts)| {
+                Point::new(
+                    tick_x(tick_index),
+                    plot_bottom
+                        - (tick_counts[specie_index] as i64 * plot_height as i64 / max_count as i64)
+                            as i32,
+                )
+            })
+            .collect();
+
+        canvas.set_draw_color(specie_color(model.get_params(), specie_index as u32 + 1));
+        canvas.draw_lines(&points[..]).unwrap();
+    }
+}
+
+/// Draws a line of text with its top left corner at the given position.
+fn draw_text(canvas: &mut Canvas<Window>, text: &str, position: Point, color: Color) {
+    let mut rects = vec![];
+
+    for (index, character) in text.chars().enumerate() {
+        let glyph_x = position.x + index as i32 * TEXT_ADVANCE;
+
+        for (row, bits) in font::glyph(character).iter().enumerate() {
+            for column in 0..font::GLYPH_WIDTH {
+                if bits & (1 << (font::GLYPH_WIDTH - 1 - column)) != 0 {
+                    rects.push(Rect::new(
+                        glyph_x + (column * TEXT_SCALE) as i32,
+                        position.y + (row as u32 * TEXT_SCALE) as i32,
+                        TEXT_SCALE,
+                        TEXT_SCALE,
+                    ));
+                }
+            }
+        }
+    }
+
+    if !rects.is_empty() {
+        canvas.set_draw_color(color);
+        canvas.fill_rects(&rects[..]).unwrap();
+    }
+}
+
+/// A line of text in a panel, optionally preceded by a color swatch.
+struct PanelLine {
+    swatch: Option<Color>,
+    text: String,
+}
+
+impl PanelLine {
+    fn text(text: String) -> PanelLine {
+        PanelLine { swatch: None, text }
+    }
+
+    /// Returns the horizontal distance in pixels between the panel's padding and the text.
+    fn indent(&self) -> i32 {
+        match self.swatch {
+            // Room for the swatch and a space
+            Some(_) => (font::GLYPH_HEIGHT * TEXT_SCALE) as i32 + TEXT_ADVANCE,
+            None => 0,
+        }
+    }
+}
+
+/// Returns the size in pixels of a panel with the given lines.
+fn panel_size(lines: &[PanelLine]) -> Size {
+    let width = lines
+        .iter()
+        .map(|line| line.indent() + line.text.chars().count() as i32 * TEXT_ADVANCE)
+        .max()
+        .unwrap_or(0)
+        + 2 * HUD_PADDING;
+    let height = lines.len() as i32 * TEXT_LINE_HEIGHT + 2 * HUD_PADDING;
+
+    Size::new(width as u32, height as u32)
+}
+
+/// Draws the lines on a translucent panel with its top left corner at the given position.
+fn draw_panel(canvas: &mut Canvas<Window>, lines: &[PanelLine], position: Point) {
+    let size = panel_size(lines);
+    let swatch_size = font::GLYPH_HEIGHT * TEXT_SCALE;
+
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(OVERLAY_BACKGROUND_COLOR);
+    canvas
+        .fill_rect(Rect::new(position.x, position.y, size.w, size.h))
+        .unwrap();
+    canvas.set_blend_mode(BlendMode::None);
+
+    let mut line_y = position.y + HUD_PADDING;
+    for line in lines {
+        let line_x = position.x + HUD_PADDING;
+
+        if let Some(color) = line.swatch {
+            canvas.set_draw_color(color);
+            canvas
+                .fill_rect(Rect::new(line_x, line_y, swatch_size, swatch_size))
+                .unwrap();
+        }
+        draw_text(
+            canvas,
+            &line.text,
+            Point::new(line_x + line.indent(), line_y),
+            TEXT_COLOR,
+        );
+        line_y += TEXT_LINE_HEIGHT;
+    }
+}
+
+/// Draws a panel in the top left corner of the canvas with the given status lines,
+/// followed by a legend with the color, name, population and share of the grid of every specie.
+pub fn draw_hud(canvas: &mut Canvas<Window>, model: &dyn Model, status_lines: &[String]) {
+    let params = model.get_params();
+    let grid_size = model.get_grid().get_size();
+    let n_cells = (grid_size.w * grid_size.h) as f32;
+
+    let mut lines: Vec<PanelLine> = status_lines
+        .iter()
+        .map(|line| PanelLine::text(line.clone()))
+        .collect();
+    // An empty line separates the status lines from the legend
+    lines.push(PanelLine::text(String::new()));
+    for (specie_name, count) in population_counts(model.get_grid(), params).iter() {
+        lines.push(PanelLine {
+            swatch: Some(specie_color(
+                params,
+                params.specie_id_from_name(specie_name),
+            )),
+            text: format!(
+                "{} {} ({:.1}%)",
+                specie_name,
+                count,
+                *count as f32 / n_cells * 100.0
+            ),
+        });
+    }
+
+    draw_panel(canvas, &lines, Point::new(0, 0));
+}
+
+/// Draws a panel with the given lines next to the mouse cursor, e.g. to inspect the cell under the cursor.
+/// The panel is moved to stay within the canvas.
+pub fn draw_tooltip(canvas: &mut Canvas<Window>, lines: &[String], mouse_position: Point) {
+    let lines: Vec<PanelLine> = lines
+        .iter()
+        .map(|line| PanelLine::text(line.clone()))
+        .collect();
+    let size = panel_size(&lines);
+    let (canvas_width, canvas_height) = canvas.output_size().unwrap();
+
+    let position = Point::new(
+        (mouse_position.x + TOOLTIP_OFFSET)
+            .min(canvas_width as i32 - size.w as i32)
+            .max(0),
+        (mouse_position.y + TOOLTIP_OFFSET)
+            .min(canvas_height as i32 - size.h as i32)
+            .max(0),
+    );
+
+    draw_panel(canvas, &lines, position);
+}
+
+/// Clears the canvas and draws a panel in its center with the given text and a progress bar, e.g. while fast-forwarding.
+/// The progress is a fraction between 0.0 and 1.0.
+pub fn draw_progress(canvas: &mut Canvas<Window>, text: &str, progress: f32) {
+    canvas.set_draw_color(BACKGROUND_COLOR);
+    canvas.clear();
+
+    // The empty line leaves room for the progress bar
+    let lines = [
+        PanelLine::text(String::from(text)),
+        PanelLine::text(String::new()),
+    ];
+    let size = panel_size(&lines);
+    let (canvas_width, canvas_height) = canvas.output_size().unwrap();
+    let position = Point::new(
+        (canvas_width as i32 - size.w as i32) / 2,
+        (canvas_height as i32 - size.h as i32) / 2,
+    );
+    draw_panel(canvas, &lines, position);
+
+    let bar_width = size.w - 2 * HUD_PADDING as u32;
+    let bar_height = font::GLYPH_HEIGHT * TEXT_SCALE;
+    let bar_position = Point::new(
+        position.x + HUD_PADDING,
+        position.y + HUD_PADDING + TEXT_LINE_HEIGHT,
+    );
+    canvas.set_draw_color(CELL_EMPTY_COLOR);
+    canvas
+        .draw_rect(Rect::new(
+            bar_position.x,
+            bar_position.y,
+            bar_width,
+            bar_height,
+        ))
+        .unwrap();
+    let filled_width = (bar_width as f32 * progress.clamp(0.0, 1.0)) as u32;
+    if filled_width > 0 {
+        canvas.set_draw_color(TEXT_COLOR);
+        canvas
+            .fill_rect(Rect::new(
+                bar_position.x,
+                bar_position.y,
+                filled_width,
+                bar_height,
+            ))
+            .unwrap();
+    }
+}
@@ -1,15 +1,29 @@
-use crate::gfx::{draw_model, View};
-use crate::models::{create_model, params::params_from_file, Model, ModelParams};
-use crate::stats::Stats;
+use crate::checkpoint::{load_checkpoint, save_checkpoint};
+use crate::gfx::{
+    canvas_to_model_coord, draw_hud, draw_model, draw_population_chart, draw_progress,
+    draw_tooltip, View,
+};
+use crate::models::events::apply_events;
+use crate::models::{
+    create_model, newborn_animal, params::params_from_file_with_seed, Cell, Model, ModelParams,
+};
+use crate::stats::{PopulationHistory, Stats};
 use crate::util::{time_ns, Size};
 use sdl2::event::{Event, WindowEvent};
-use sdl2::keyboard::Scancode;
+use sdl2::keyboard::{Mod, Scancode};
+use sdl2::mouse::MouseButton;
+use sdl2::rect::Point;
+use sdl2::render::WindowCanvas;
 
 /// Controls the time aspect of the simulation, e.g. how fast the simulation should run, whether the simulation is paused or not.
+/// The simulation can also run until a target tick, either at the target speed or as fast as possible (fast-forward).
 struct TimeController {
     ticks_per_second: f32,
     running: bool,
     leftover_seconds: f32,
+    pending_steps: u32,             // Ticks to run while paused, one per step
+    target: Option<(usize, usize)>, // Tick at which the run to the target started and the target tick, None if there is no target
+    fast_forward: bool,             // Whether to run to the target tick as fast as possible
 }
 
 impl TimeController {
@@ -18,26 +32,94 @@ impl TimeController {
             ticks_per_second: 1.0,
             running: true,
             leftover_seconds: 0.0,
+            pending_steps: 0,
+            target: None,
+            fast_forward: false,
         }
     }
 
     /// Updates the time and returns the number of times model.tick() should be called in this frame.
-    pub fn update(&mut self, seconds_elapsed: f32) -> u32 {
+    /// Pauses the simulation once the target tick has been reached.
+    pub fn update(&mut self, seconds_elapsed: f32, ticks_elapsed: usize) -> u32 {
+        let remaining_ticks = match self.target {
+            Some((_, target_tick)) if ticks_elapsed >= target_tick => {
+                self.cancel_target();
+                self.running = false;
+                println!("reached tick {}, model paused", target_tick);
+                return 0;
+            }
+            Some((_, target_tick)) => {
+                Some((target_tick - ticks_elapsed).min(u32::MAX as usize) as u32)
+            }
+            None => None,
+        };
+
+        if self.is_fast_forwarding() {
+            return remaining_ticks.unwrap();
+        }
+
+        let mut ticks = 0u32;
         if self.running {
             self.leftover_seconds += seconds_elapsed;
 
-            let mut ticks = 0u32;
             while self.leftover_seconds >= (1.0 / self.ticks_per_second) {
                 ticks += 1;
                 self.leftover_seconds -= 1.0 / self.ticks_per_second;
             }
+        } else {
+            ticks = self.pending_steps;
+        }
+        self.pending_steps = 0;
 
-            ticks
+        match remaining_ticks {
+            Some(remaining_ticks) => ticks.min(remaining_ticks),
+            None => ticks,
+        }
+    }
+
+    /// Runs a single tick in the next frame, if the simulation is paused.
+    pub fn step(&mut self) {
+        if !self.running {
+            self.pending_steps += 1;
+        }
+    }
+
+    /// Runs the simulation until the target tick and pauses it there.
+    /// When fast-forwarding, the simulation runs as fast as possible instead of at the target speed.
+    pub fn run_until(&mut self, ticks_elapsed: usize, target_tick: usize, fast_forward: bool) {
+        if target_tick <= ticks_elapsed {
+            println!("tick {} has already been reached", target_tick);
+            return;
+        }
+
+        self.target = Some((ticks_elapsed, target_tick));
+        self.fast_forward = fast_forward;
+        self.running = true;
+        self.leftover_seconds = 0.0;
+        if fast_forward {
+            println!("fast-forwarding to tick {}", target_tick);
         } else {
-            0
+            println!("running until tick {}", target_tick);
         }
     }
 
+    /// Stops running to the target tick, without pausing the simulation.
+    pub fn cancel_target(&mut self) {
+        self.target = None;
+        self.fast_forward = false;
+    }
+
+    /// Returns the tick at which the run to the target started and the target tick, if there is a target.
+    pub fn get_target(&self) -> Option<(usize, usize)> {
+        self.target
+    }
+
+    /// Returns whether the simulation is running to the target tick as fast as possible.
+    /// While paused, the model is shown as usual.
+    pub fn is_fast_forwarding(&self) -> bool {
+        self.fast_forward && self.running
+    }
+
     pub fn toggle_paused(&mut self) {
         self.running = !self.running;
 
@@ -49,14 +131,14 @@ impl TimeController {
     }
 
     pub fn increase_speed(&mut self) {
-        if (self.ticks_per_second < MAX_TICKS_PER_SECOND) {
+        if self.ticks_per_second < MAX_TICKS_PER_SECOND {
             self.ticks_per_second *= 2.0;
             println!("target ticks per second: {}", self.ticks_per_second);
         }
     }
 
     pub fn decrease_speed(&mut self) {
-        if (self.ticks_per_second > MIN_TICKS_PER_SECOND) {
+        if self.ticks_per_second > MIN_TICKS_PER_SECOND {
             self.ticks_per_second /= 2.0;
             println!("target ticks per second: {}", self.ticks_per_second);
         }
@@ -65,21 +147,25 @@ impl TimeController {
     pub fn is_running(&self) -> bool {
         self.running
     }
+
+    pub fn get_ticks_per_second(&self) -> f32 {
+        self.ticks_per_second
+    }
 }
 
 /// Records the ticks that have been elapsed and prints the actual simulation speed every second.
 struct TickRecorder {
-    total_ticks: usize,
     leftover_seconds: f32,
     elapsed_ticks: usize,
+    ticks_per_second: usize, // Actual simulation speed during the last second
 }
 
 impl TickRecorder {
     pub fn new() -> TickRecorder {
         TickRecorder {
-            total_ticks: 0,
             leftover_seconds: 0.0,
             elapsed_ticks: 0,
+            ticks_per_second: 0,
         }
     }
 
@@ -88,6 +174,7 @@ impl TickRecorder {
             if self.elapsed_ticks > 0 {
                 println!("ticks per second: {}", self.elapsed_ticks);
             }
+            self.ticks_per_second = self.elapsed_ticks;
             self.elapsed_ticks = 0;
             self.leftover_seconds += 1.0;
         }
@@ -100,12 +187,153 @@ impl TickRecorder {
     }
 
     pub fn tick(&mut self) {
-        self.total_ticks += 1;
         self.elapsed_ticks += 1;
     }
 
-    pub fn get_total(&self) -> usize {
-        self.total_ticks
+    pub fn get_ticks_per_second(&self) -> usize {
+        self.ticks_per_second
+    }
+}
+
+/// A target tick that is being typed, to run the simulation until that tick.
+struct TickInput {
+    digits: String,
+    fast_forward: bool,
+}
+
+impl TickInput {
+    pub fn new(fast_forward: bool) -> TickInput {
+        TickInput {
+            digits: String::new(),
+            fast_forward,
+        }
+    }
+
+    pub fn push_digit(&mut self, digit: char) {
+        if self.digits.len() < MAX_TICK_INPUT_DIGITS {
+            self.digits.push(digit);
+        }
+    }
+
+    pub fn pop_digit(&mut self) {
+        self.digits.pop();
+    }
+
+    /// Returns the typed tick, None if no digits have been typed.
+    pub fn get_tick(&self) -> Option<usize> {
+        self.digits.parse().ok()
+    }
+
+    pub fn is_fast_forward(&self) -> bool {
+        self.fast_forward
+    }
+
+    /// Returns the line that shows the typed tick in the HUD.
+    pub fn prompt(&self) -> String {
+        let action = if self.fast_forward {
+            "Fast-forward to"
+        } else {
+            "Run until"
+        };
+
+        format!("{} tick: {}_", action, self.digits)
+    }
+}
+
+/// The specie and size of the brush that paints cells with the mouse.
+struct Brush {
+    specie_id: u32,
+    radius: u32, // Cells within this distance (in cells) of the cell under the cursor are painted
+    last_position: Option<Point>, // Cell that was painted last while dragging, None if no mouse button is held
+}
+
+impl Brush {
+    pub fn new() -> Brush {
+        Brush {
+            specie_id: 1,
+            radius: 0,
+            last_position: None,
+        }
+    }
+
+    pub fn select_specie(&mut self, specie_id: u32, params: &ModelParams) {
+        if specie_id >= 1 && specie_id <= params.species.len() as u32 {
+            self.specie_id = specie_id;
+            println!("brush: {}", params.specie_name_from_id(specie_id));
+        }
+    }
+
+    pub fn increase_size(&mut self) {
+        if self.radius < MAX_BRUSH_RADIUS {
+            self.radius += 1;
+            println!("brush radius: {}", self.radius);
+        }
+    }
+
+    pub fn decrease_size(&mut self) {
+        if self.radius > 0 {
+            self.radius -= 1;
+            println!("brush radius: {}", self.radius);
+        }
+    }
+
+    /// Paints the selected specie (or empty cells if erasing) around the given cell.
+    /// While dragging, also paints the cells in between the previous and the given cell, so that fast strokes have no gaps.
+    pub fn paint(&mut self, model: &mut Box<dyn Model>, position: Point, erase: bool) {
+        let from = self.last_position.unwrap_or(position);
+        let n_steps = (position.x - from.x)
+            .abs()
+            .max((position.y - from.y).abs())
+            .max(1);
+
+        for step in 1..=n_steps {
+            let center = Point::new(
+                from.x + (position.x - from.x) * step / n_steps,
+                from.y + (position.y - from.y) * step / n_steps,
+            );
+            self.paint_at(model, center, erase);
+        }
+        self.last_position = Some(position);
+    }
+
+    pub fn release(&mut self) {
+        self.last_position = None;
+    }
+
+    fn paint_at(&self, model: &mut Box<dyn Model>, center: Point, erase: bool) {
+        let new_cell = if erase {
+            Cell::Empty
+        } else {
+            newborn_animal(self.specie_id, model.get_params())
+        };
+        let grid = model.get_grid_mut();
+        let grid_size = grid.get_size();
+        let radius = self.radius as i32;
+
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let (x, y) = (center.x + dx, center.y + dy);
+                if dx * dx + dy * dy > radius * radius
+                    || x < 0
+                    || y < 0
+                    || x >= grid_size.w as i32
+                    || y >= grid_size.h as i32
+                {
+                    continue;
+                }
+
+                // Animals of the painted specie keep their state
+                let is_same_specie = match (grid.get_cell_at(x as u32, y as u32), &new_cell) {
+                    (&Cell::Animal(specie_id, _), &Cell::Animal(new_specie_id, _)) => {
+                        specie_id == new_specie_id
+                    }
+                    _ => false,
+                };
+                if !is_same_specie {
+                    grid.set_cell_at(x as u32, y as u32, new_cell.clone());
+                }
+            }
+        }
     }
 }
 
@@ -113,26 +341,131 @@ const ENABLE_VSYNC: bool = true;
 const WINDOW_SIZE: Size = Size::new(800, 600);
 const MIN_TICKS_PER_SECOND: f32 = 0.25;
 const MAX_TICKS_PER_SECOND: f32 = 134217730f32;
+const MAX_BRUSH_RADIUS: u32 = 32;
+const PAN_DISTANCE_PIXELS: i32 = 64;
+// Number of ticks shown in the population chart.
+const POPULATION_HISTORY_LENGTH: usize = 500;
+// Number keys that select the brush specie, in order of specie id.
+const SPECIE_SCANCODES: [Scancode; 9] = [
+    Scancode::Num1,
+    Scancode::Num2,
+    Scancode::Num3,
+    Scancode::Num4,
+    Scancode::Num5,
+    Scancode::Num6,
+    Scancode::Num7,
+    Scancode::Num8,
+    Scancode::Num9,
+];
 // Number of seconds (target) for processing model behavior per frame, before continuing on.
 const MODEL_TIME_PER_FRAME_THRESHOLD_SEC: f32 = 0.025;
+// Same as above while fast-forwarding, which only draws the progress and can therefore spend most of a frame on the model.
+const FAST_FORWARD_TIME_PER_FRAME_THRESHOLD_SEC: f32 = 0.2;
+const MAX_TICK_INPUT_DIGITS: usize = 12;
+
+/// Returns the window title, which includes the random seed so that the run can be reproduced.
+fn window_title(model: &dyn Model) -> String {
+    format!("Onderzoeksmethoden (seed {})", model.get_seed())
+}
+
+fn set_window_title(canvas: &mut WindowCanvas, model: &dyn Model) {
+    if let Err(error) = canvas.window_mut().set_title(&window_title(model)) {
+        println!("Failed to set window title: {}", error);
+    }
+}
+
+fn canvas_size(canvas: &WindowCanvas) -> Size {
+    let (canvas_width, canvas_height) = canvas.output_size().unwrap();
+
+    Size::new(canvas_width, canvas_height)
+}
+
+/// Returns the position of the cell under the mouse cursor.
+fn mouse_to_model_coord(
+    canvas: &WindowCanvas,
+    model: &dyn Model,
+    view: &View,
+    x: i32,
+    y: i32,
+) -> Point {
+    canvas_to_model_coord(
+        Point::new(x, y),
+        canvas_size(canvas),
+        view,
+        model.get_grid().get_topology(),
+    )
+}
+
+/// Returns the digit typed with the given key, on the number row or on the keypad.
+fn digit_from_scancode(scancode: Scancode) -> Option<char> {
+    match scancode {
+        Scancode::Num0 | Scancode::Kp0 => Some('0'),
+        Scancode::Num1 | Scancode::Kp1 => Some('1'),
+        Scancode::Num2 | Scancode::Kp2 => Some('2'),
+        Scancode::Num3 | Scancode::Kp3 => Some('3'),
+        Scancode::Num4 | Scancode::Kp4 => Some('4'),
+        Scancode::Num5 | Scancode::Kp5 => Some('5'),
+        Scancode::Num6 | Scancode::Kp6 => Some('6'),
+        Scancode::Num7 | Scancode::Kp7 => Some('7'),
+        Scancode::Num8 | Scancode::Kp8 => Some('8'),
+        Scancode::Num9 | Scancode::Kp9 => Some('9'),
+        _ => None,
+    }
+}
+
+/// Returns the number of cells to pan by per key press, which covers the same distance on screen at any scale.
+fn pan_distance(view: &View) -> i32 {
+    (PAN_DISTANCE_PIXELS / view.scale as i32).max(1)
+}
 
 /// The main (GUI) loop of the program.
 /// Creates an SDL2 window and runs an event loop.
-pub fn main_loop(config_path: &str, stats_path: Option<&str>) {
-    let model_params = params_from_file(config_path).expect("Failed to load parameters");
-    let mut model: Box<dyn Model> = create_model(model_params);
-    model.populate();
-    let mut stats = stats_path.map(|path| Stats::new(path));
-    let mut ticks_elapsed = 0;
+/// Starts from the given checkpoint if one is given, otherwise creates a new model from the parameters file.
+/// Resetting the model restarts it from the checkpoint's parameters after a checkpoint has been loaded,
+/// otherwise it reloads the parameters file. The random seed overrides the seed in either, also when the model is reset.
+pub fn main_loop(
+    config_path: &str,
+    stats_path: Option<&str>,
+    resume_path: Option<&str>,
+    checkpoint_path: &str,
+    random_seed: Option<u64>,
+) {
+    let (mut model, mut ticks_elapsed) = match resume_path {
+        Some(resume_path) => load_checkpoint(resume_path).expect("Failed to load checkpoint"),
+        None => {
+            let model_params = params_from_file_with_seed(config_path, random_seed)
+                .expect("Failed to load parameters");
+            let mut model: Box<dyn Model> =
+                create_model(model_params).expect("Failed to create model");
+            model.populate().expect("Failed to populate model");
+            (model, 0)
+        }
+    };
+    // Parameters of the loaded checkpoint, None if the model was created from the parameters file
+    let mut checkpoint_params: Option<ModelParams> =
+        resume_path.map(|_| model.get_params().clone());
+    println!("random seed: {}", model.get_seed());
+    let mut stats = stats_path.map(Stats::new);
 
     let mut time_controller = TimeController::new();
     let mut tick_recorder = TickRecorder::new();
+    let mut brush = Brush::new();
+    let mut population_history = PopulationHistory::new(POPULATION_HISTORY_LENGTH);
+    population_history.record(model.as_ref());
+    let mut show_population_chart = false;
+    let mut show_hud = true;
+    let mut show_inspector = false;
+    // Target tick that is being typed, None if no target tick is being typed
+    let mut tick_input: Option<TickInput> = None;
+    let mut mouse_position = Point::new(0, 0);
+    // Mouse position and view midpoint at the start of dragging the view, None if the view is not being dragged
+    let mut drag_start: Option<(Point, Point)> = None;
     let mut view = View::default(model.get_grid().get_size());
 
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
     let window = video_subsystem
-        .window("Onderzoeksmethoden", WINDOW_SIZE.w, WINDOW_SIZE.h)
+        .window(&window_title(model.as_ref()), WINDOW_SIZE.w, WINDOW_SIZE.h)
         .position_centered()
         .resizable()
         .build()
@@ -157,53 +490,214 @@ pub fn main_loop(config_path: &str, stats_path: Option<&str>) {
                     break 'event_loop;
                 }
 
-                Event::Window { win_event, .. } => match win_event {
-                    WindowEvent::SizeChanged(_width, _height) => {
-                        // Note: Window size may be used for later optimizations when using large grid sizes.
-                    }
-                    _ => {}
-                },
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(_width, _height),
+                    ..
+                } => {
+                    // Note: Window size may be used for later optimizations when using large grid sizes.
+                }
 
                 Event::KeyDown {
                     scancode: Some(scancode),
+                    keymod,
                     ..
                 } => {
+                    let ctrl_pressed = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+                    let grid_size = model.get_grid().get_size();
+
+                    // While typing a target tick, keys edit the target tick instead of controlling the simulation
+                    if let Some(input) = &mut tick_input {
+                        if let Some(digit) = digit_from_scancode(scancode) {
+                            input.push_digit(digit);
+                        } else if scancode == Scancode::Backspace {
+                            input.pop_digit();
+                        } else if scancode == Scancode::Return || scancode == Scancode::KpEnter {
+                            match input.get_tick() {
+                                Some(target_tick) => time_controller.run_until(
+                                    ticks_elapsed,
+                                    target_tick,
+                                    input.is_fast_forward(),
+                                ),
+                                None => println!("no target tick entered"),
+                            }
+                            tick_input = None;
+                        } else if scancode == Scancode::Escape {
+                            tick_input = None;
+                        }
+                        continue;
+                    }
+
                     if scancode == Scancode::R {
-                        // Reload parameters from file and repopulate model
-                        match params_from_file(config_path) {
-                            Ok(params) => {
-                                model = create_model(params.clone());
-                                model.populate();
+                        // Reload parameters from file (or take them from the loaded checkpoint) and repopulate model
+                        let new_params = match &checkpoint_params {
+                            Some(params) => {
+                                let mut params = params.clone();
+                                if random_seed.is_some() {
+                                    params.random_seed = random_seed;
+                                }
+                                Ok(params)
+                            }
+                            None => params_from_file_with_seed(config_path, random_seed),
+                        };
+                        let new_model = new_params.and_then(|params| {
+                            let mut new_model = create_model(params)?;
+                            new_model.populate()?;
+                            Ok(new_model)
+                        });
+                        match new_model {
+                            Ok(new_model) => {
+                                model = new_model;
+                                set_window_title(&mut canvas, model.as_ref());
 
                                 if let Some(stats) = &mut stats {
                                     stats.reset();
                                 }
+                                population_history.clear();
+                                population_history.record(model.as_ref());
                                 ticks_elapsed = 0;
-                                println!("model reset");
+                                time_controller.cancel_target();
+                                println!("model reset, random seed: {}", model.get_seed());
+                            }
+                            Err(error) => {
+                                println!("Failed to reset model: {}", error);
+                            }
+                        }
+                    } else if scancode == Scancode::S && ctrl_pressed {
+                        match save_checkpoint(checkpoint_path, model.as_ref(), ticks_elapsed) {
+                            Ok(()) => println!("checkpoint saved to {}", checkpoint_path),
+                            Err(error) => println!("Failed to save checkpoint: {}", error),
+                        }
+                    } else if scancode == Scancode::L && ctrl_pressed {
+                        // Replace the model by the one in the checkpoint and continue the statistics
+                        match load_checkpoint(checkpoint_path) {
+                            Ok((checkpoint_model, checkpoint_ticks_elapsed)) => {
+                                model = checkpoint_model;
+                                checkpoint_params = Some(model.get_params().clone());
+                                ticks_elapsed = checkpoint_ticks_elapsed;
+                                set_window_title(&mut canvas, model.as_ref());
+
+                                if let Some(stats) = &mut stats {
+                                    if let Err(error) = stats.resume(ticks_elapsed, model.as_ref())
+                                    {
+                                        println!("Failed to write statistics: {}", error);
+                                    }
+                                }
+                                population_history.clear();
+                                population_history.record(model.as_ref());
+                                time_controller.cancel_target();
+                                println!("checkpoint loaded from {}", checkpoint_path);
                             }
                             Err(error) => {
-                                println!("Failed to load parameters: {}", error);
+                                println!("Failed to load checkpoint: {}", error);
                             }
                         }
                     } else if scancode == Scancode::Space {
                         time_controller.toggle_paused();
+                    } else if scancode == Scancode::N {
+                        time_controller.step();
+                    } else if scancode == Scancode::G {
+                        tick_input = Some(TickInput::new(false));
+                    } else if scancode == Scancode::J {
+                        tick_input = Some(TickInput::new(true));
+                    } else if scancode == Scancode::Escape {
+                        if let Some((_, target_tick)) = time_controller.get_target() {
+                            time_controller.cancel_target();
+                            println!("stopped running to tick {}", target_tick);
+                        }
                     } else if scancode == Scancode::Comma {
                         time_controller.decrease_speed();
                         tick_recorder.reset();
                     } else if scancode == Scancode::Period {
                         time_controller.increase_speed();
                         tick_recorder.reset();
+                    } else if scancode == Scancode::Left || scancode == Scancode::A {
+                        view.pan(-pan_distance(&view), 0, grid_size);
+                    } else if scancode == Scancode::Right || scancode == Scancode::D {
+                        view.pan(pan_distance(&view), 0, grid_size);
+                    } else if scancode == Scancode::Up || scancode == Scancode::W {
+                        view.pan(0, -pan_distance(&view), grid_size);
+                    } else if scancode == Scancode::Down || scancode == Scancode::S {
+                        view.pan(0, pan_distance(&view), grid_size);
+                    } else if scancode == Scancode::I {
+                        show_inspector = !show_inspector;
+                    } else if scancode == Scancode::H {
+                        show_hud = !show_hud;
+                    } else if scancode == Scancode::C {
+                        show_population_chart = !show_population_chart;
+                        if show_population_chart {
+                            // The history is only recorded while the chart is shown, so start it anew
+                            population_history.clear();
+                            population_history.record(model.as_ref());
+                        }
+                    } else if scancode == Scancode::F {
+                        view.fit(
+                            canvas_size(&canvas),
+                            grid_size,
+                            model.get_grid().get_topology(),
+                        );
+                    } else if scancode == Scancode::LeftBracket {
+                        brush.decrease_size();
+                    } else if scancode == Scancode::RightBracket {
+                        brush.increase_size();
+                    } else if let Some(index) = SPECIE_SCANCODES
+                        .iter()
+                        .position(|specie_scancode| *specie_scancode == scancode)
+                    {
+                        brush.select_specie(index as u32 + 1, model.get_params());
+                    }
+                }
+
+                Event::MouseButtonDown {
+                    mouse_btn, x, y, ..
+                } => {
+                    // Paint with the left mouse button, erase with the right mouse button and pan with the middle mouse button
+                    if mouse_btn == MouseButton::Left || mouse_btn == MouseButton::Right {
+                        let position = mouse_to_model_coord(&canvas, model.as_ref(), &view, x, y);
+                        brush.paint(&mut model, position, mouse_btn == MouseButton::Right);
+                    } else if mouse_btn == MouseButton::Middle {
+                        drag_start = Some((Point::new(x, y), view.midpoint));
+                    }
+                }
+
+                Event::MouseMotion {
+                    mousestate, x, y, ..
+                } => {
+                    mouse_position = Point::new(x, y);
+
+                    if mousestate.left() || mousestate.right() {
+                        let position = mouse_to_model_coord(&canvas, model.as_ref(), &view, x, y);
+                        brush.paint(&mut model, position, !mousestate.left());
+                    }
+                    if let Some((start_position, start_midpoint)) = drag_start {
+                        view.drag(
+                            start_midpoint,
+                            x - start_position.x,
+                            y - start_position.y,
+                            model.get_grid().get_size(),
+                            model.get_grid().get_topology(),
+                        );
                     }
                 }
 
-                Event::MouseWheel { y, .. } => {
-                    if y < 0 {
-                        view.decrease_scale();
-                    } else if y > 0 {
-                        view.increase_scale();
+                Event::MouseButtonUp { mouse_btn, .. } => {
+                    if mouse_btn == MouseButton::Middle {
+                        drag_start = None;
+                    } else {
+                        brush.release();
                     }
                 }
 
+                Event::MouseWheel { y, .. } if y != 0 => {
+                    // Zoom in or out around the cell under the mouse cursor
+                    view.zoom_at(
+                        mouse_position,
+                        canvas_size(&canvas),
+                        model.get_grid().get_size(),
+                        model.get_grid().get_topology(),
+                        y > 0,
+                    );
+                }
+
                 _ => {}
             }
         }
@@ -214,28 +708,105 @@ pub fn main_loop(config_path: &str, stats_path: Option<&str>) {
         // Clamp the elapsed time in this frame between 1 nanosecond and 1 second to prevent divide by zero and runaway.
         let seconds_elapsed: f32 = raw_seconds_elapsed.clamp(1e-9f32, 1.0);
 
-        let target_ticks = time_controller.update(seconds_elapsed);
+        let target_ticks = time_controller.update(seconds_elapsed, ticks_elapsed);
+        let time_per_frame_threshold_sec = if time_controller.is_fast_forwarding() {
+            FAST_FORWARD_TIME_PER_FRAME_THRESHOLD_SEC
+        } else {
+            MODEL_TIME_PER_FRAME_THRESHOLD_SEC
+        };
         if time_controller.is_running() {
             tick_recorder.update(seconds_elapsed);
         }
         for _ in 0..target_ticks {
             model.tick();
+            ticks_elapsed += 1;
             tick_recorder.tick();
+            for description in apply_events(&mut model, ticks_elapsed) {
+                println!("tick {}: {}", ticks_elapsed, description);
+                if let Some(stats) = &mut stats {
+                    if let Err(error) = stats.log_event(ticks_elapsed, model.as_ref(), &description)
+                    {
+                        println!("Failed to log event: {}", error);
+                    }
+                }
+            }
             if let Some(stats) = &mut stats {
-                stats.collect(
-                    tick_recorder.get_total(),
-                    model.get_grid(),
-                    model.get_params(),
-                );
+                if let Err(error) = stats.collect(ticks_elapsed, model.as_ref()) {
+                    println!("Failed to collect stats: {}", error);
+                }
+            }
+            // Counting the population takes a pass over the grid, so only do it when the chart needs it
+            if show_population_chart {
+                population_history.record(model.as_ref());
             }
 
-            if (time_ns() - cur_nano_time) as f32 / 1e9f32 > MODEL_TIME_PER_FRAME_THRESHOLD_SEC {
+            if (time_ns() - cur_nano_time) as f32 / 1e9f32 > time_per_frame_threshold_sec {
                 // Skip processing any more ticks, continue.
                 break;
             }
         }
 
-        draw_model(&mut canvas, &model, &view);
+        if time_controller.is_fast_forwarding() {
+            // Skip drawing the model while fast-forwarding, only show the progress
+            let (start_tick, target_tick) = time_controller.get_target().unwrap();
+            let text = format!(
+                "Tick {} / {} ({} ticks/s)",
+                ticks_elapsed,
+                target_tick,
+                tick_recorder.get_ticks_per_second()
+            );
+            let progress = (ticks_elapsed - start_tick) as f32 / (target_tick - start_tick) as f32;
+            draw_progress(&mut canvas, &text, progress);
+            canvas.present();
+            continue;
+        }
+
+        draw_model(&mut canvas, model.as_ref(), &view);
+        if show_population_chart {
+            draw_population_chart(&mut canvas, model.as_ref(), &population_history);
+        }
+        if show_hud || tick_input.is_some() {
+            let mut status_lines = vec![
+                format!("Tick {}", ticks_elapsed),
+                format!(
+                    "Ticks/s {} / {}",
+                    tick_recorder.get_ticks_per_second(),
+                    time_controller.get_ticks_per_second()
+                ),
+                format!("Seed {}", model.get_seed()),
+                String::from(if time_controller.is_running() {
+                    "Running"
+                } else {
+                    "Paused"
+                }),
+            ];
+            if let Some((_, target_tick)) = time_controller.get_target() {
+                status_lines.push(format!("Running until tick {}", target_tick));
+            }
+            if let Some(input) = &tick_input {
+                status_lines.push(input.prompt());
+            }
+            draw_hud(&mut canvas, model.as_ref(), &status_lines);
+        }
+        if show_inspector {
+            // Describe the cell under the mouse cursor
+            let position = mouse_to_model_coord(
+                &canvas,
+                model.as_ref(),
+                &view,
+                mouse_position.x,
+                mouse_position.y,
+            );
+            let grid_size = model.get_grid().get_size();
+            if position.x >= 0
+                && position.y >= 0
+                && position.x < grid_size.w as i32
+                && position.y < grid_size.h as i32
+            {
+                let lines = model.inspect_cell(position.x as u32, position.y as u32);
+                draw_tooltip(&mut canvas, &lines, mouse_position);
+            }
+        }
         canvas.present();
     }
 }
@@ -0,0 +1,102 @@
+use crate::checkpoint::{load_checkpoint, save_checkpoint};
+use crate::models::events::apply_events;
+use crate::models::{create_model, params::params_from_file_with_seed, Model, ModelParams};
+use crate::stats::Stats;
+use crate::util::time_ns;
+use std::error::Error;
+
+// Number of ticks between progress reports.
+const PROGRESS_INTERVAL: usize = 100;
+
+/// Runs the model without opening a window.
+/// Loads the parameters (or resumes from a checkpoint), then runs the given number of ticks as fast as possible while collecting statistics.
+/// The random seed from the parameters can be overridden, which has no effect when resuming from a checkpoint.
+/// Optionally saves a checkpoint after the last tick.
+pub fn run(
+    config_path: &str,
+    stats_path: Option<&str>,
+    ticks: usize,
+    resume_path: Option<&str>,
+    checkpoint_path: Option<&str>,
+    random_seed: Option<u64>,
+) -> Result<(), Box<dyn Error>> {
+    let (mut model, start_ticks) = match resume_path {
+        Some(resume_path) => load_checkpoint(resume_path)?,
+        None => {
+            let model_params = params_from_file_with_seed(config_path, random_seed)?;
+            let mut model: Box<dyn Model> = create_model(model_params)?;
+            model.populate()?;
+            (model, 0)
+        }
+    };
+    println!("random seed: {}", model.get_seed());
+    let mut stats = stats_path.map(Stats::new);
+
+    let start_nano_time = time_ns();
+
+    run_model(&mut model, &mut stats, start_ticks, ticks, true)?;
+
+    let seconds_elapsed = (time_ns() - start_nano_time) as f32 / 1e9f32;
+    println!(
+        "finished {} ticks in {:.2} seconds ({:.1} ticks per second)",
+        ticks,
+        seconds_elapsed,
+        ticks as f32 / seconds_elapsed
+    );
+
+    if let Some(checkpoint_path) = checkpoint_path {
+        save_checkpoint(checkpoint_path, model.as_ref(), start_ticks + ticks)?;
+        println!("checkpoint saved to {}", checkpoint_path);
+    }
+
+    Ok(())
+}
+
+/// Creates and populates a model from the given parameters, then runs the given number of ticks while collecting statistics.
+/// Returns the random seed that the model used.
+pub fn run_params(
+    model_params: ModelParams,
+    stats_path: Option<&str>,
+    ticks: usize,
+    print_progress: bool,
+) -> Result<u64, Box<dyn Error>> {
+    let mut model: Box<dyn Model> = create_model(model_params)?;
+    model.populate()?;
+    let mut stats = stats_path.map(Stats::new);
+
+    run_model(&mut model, &mut stats, 0, ticks, print_progress)?;
+
+    Ok(model.get_seed())
+}
+
+/// Runs the given number of ticks on a model that has already run for `start_ticks` ticks.
+fn run_model(
+    model: &mut Box<dyn Model>,
+    stats: &mut Option<Stats>,
+    start_ticks: usize,
+    ticks: usize,
+    print_progress: bool,
+) -> Result<(), Box<dyn Error>> {
+    let end_ticks = start_ticks + ticks;
+
+    for ticks_elapsed in (start_ticks + 1)..(end_ticks + 1) {
+        model.tick();
+        for description in apply_events(model, ticks_elapsed) {
+            if print_progress {
+                println!("tick {}: {}", ticks_elapsed, description);
+            }
+            if let Some(stats) = stats {
+                stats.log_event(ticks_elapsed, model.as_ref(), &description)?;
+            }
+        }
+        if let Some(stats) = stats {
+            stats.collect(ticks_elapsed, model.as_ref())?;
+        }
+
+        if print_progress && ticks_elapsed % PROGRESS_INTERVAL == 0 {
+            println!("tick {}/{}", ticks_elapsed, end_ticks);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,19 @@
+//! Cellular automaton models of the interactions between species in a food web.
+//!
+//! A model is created from its parameters with `models::create_model`, or directly from a JSON config with
+//! `models::create_model_from_str`. Each call to `Model::tick` advances it by one time step, after which its cells can be
+//! inspected through `Model::get_grid`. The `stats` module counts the population of every specie and writes it to a CSV file.
+//!
+//! The SDL2 window (the `gfx` and `gui` modules) is only available with the `gui` feature, which is enabled by default.
+
+pub mod checkpoint;
+pub mod coords;
+#[cfg(feature = "gui")]
+pub mod gfx;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod headless;
+pub mod models;
+pub mod stats;
+pub mod sweep;
+pub mod util;
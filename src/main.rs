@@ -1,29 +1,135 @@
-mod gfx;
-mod gui;
-mod models;
-mod stats;
-mod util;
-
-extern crate sdl2;
+#[cfg(feature = "gui")]
+use onderzoeksmethoden::gui;
+use onderzoeksmethoden::{headless, sweep};
 
 const DEFAULT_CONFIG_PATH: &str = "sample-configs/simple-fish.json";
+#[cfg(feature = "gui")]
+const DEFAULT_CHECKPOINT_PATH: &str = "checkpoint.json";
+
+fn print_usage(program: &str) {
+    println!(
+        "Usage: {} [--headless <ticks>] [--sweep <path/to/sweep.json>] [--resume <path/to/checkpoint.json>] [--checkpoint <path/to/checkpoint.json>] [--seed <seed>] [path/to/config.json] [path/to/stats.csv]",
+        program
+    );
+}
 
 fn main() {
     let arguments = std::env::args().collect::<Vec<String>>();
-    if arguments.len() < 1 {
-        println!(
-            "Usage: {} [path/to/config.json] [path/to/stats.csv]",
-            arguments[0]
-        );
+    if arguments.is_empty() {
+        print_usage("onderzoeksmethoden");
         std::process::exit(1);
     }
-    let config_path = if arguments.len() >= 2 { &arguments[1] } else { DEFAULT_CONFIG_PATH };
-    let stats_path = if arguments.len() >= 3 {
-        Some(arguments[2].as_str())
+
+    // Split the arguments into options (--name value) and positional arguments
+    let mut headless_ticks: Option<usize> = None;
+    let mut sweep_path: Option<&str> = None;
+    let mut resume_path: Option<&str> = None;
+    let mut checkpoint_path: Option<&str> = None;
+    let mut random_seed: Option<u64> = None;
+    let mut positional_arguments = vec![];
+    let mut i = 1;
+    while i < arguments.len() {
+        if arguments[i] == "--headless" && i + 1 < arguments.len() {
+            match arguments[i + 1].parse::<usize>() {
+                Ok(ticks) => headless_ticks = Some(ticks),
+                Err(_) => {
+                    println!("Invalid number of ticks: {}", arguments[i + 1]);
+                    std::process::exit(1);
+                }
+            }
+            i += 2;
+        } else if arguments[i] == "--sweep" && i + 1 < arguments.len() {
+            sweep_path = Some(arguments[i + 1].as_str());
+            i += 2;
+        } else if arguments[i] == "--resume" && i + 1 < arguments.len() {
+            resume_path = Some(arguments[i + 1].as_str());
+            i += 2;
+        } else if arguments[i] == "--checkpoint" && i + 1 < arguments.len() {
+            checkpoint_path = Some(arguments[i + 1].as_str());
+            i += 2;
+        } else if arguments[i] == "--seed" && i + 1 < arguments.len() {
+            match arguments[i + 1].parse::<u64>() {
+                Ok(seed) => random_seed = Some(seed),
+                Err(_) => {
+                    println!("Invalid random seed: {}", arguments[i + 1]);
+                    std::process::exit(1);
+                }
+            }
+            i += 2;
+        } else if arguments[i].starts_with("--") {
+            print_usage(&arguments[0]);
+            std::process::exit(1);
+        } else {
+            positional_arguments.push(arguments[i].as_str());
+            i += 1;
+        }
+    }
+
+    let config_path = if !positional_arguments.is_empty() {
+        positional_arguments[0]
+    } else {
+        DEFAULT_CONFIG_PATH
+    };
+    let stats_path = if positional_arguments.len() >= 2 {
+        Some(positional_arguments[1])
     } else {
         None
     };
 
-    println!("\nsimulation controls:\n  R: restart\n  ,/.: decrease/increase speed\n  scroll wheel: decrease/increase scale\n  space: toggle pause/resume\n");
-    gui::main_loop(config_path, stats_path);
+    if let Some(sweep_path) = sweep_path {
+        if let Err(error) = sweep::run(sweep_path, config_path, random_seed) {
+            println!("Parameter sweep failed: {}", error);
+            std::process::exit(1);
+        }
+    } else if let Some(ticks) = headless_ticks {
+        if let Err(error) = headless::run(
+            config_path,
+            stats_path,
+            ticks,
+            resume_path,
+            checkpoint_path,
+            random_seed,
+        ) {
+            println!("Headless run failed: {}", error);
+            std::process::exit(1);
+        }
+    } else {
+        run_gui(
+            config_path,
+            stats_path,
+            resume_path,
+            checkpoint_path,
+            random_seed,
+        );
+    }
+}
+
+#[cfg(feature = "gui")]
+fn run_gui(
+    config_path: &str,
+    stats_path: Option<&str>,
+    resume_path: Option<&str>,
+    checkpoint_path: Option<&str>,
+    random_seed: Option<u64>,
+) {
+    println!("\nsimulation controls:\n  R: restart\n  ,/.: decrease/increase speed\n  scroll wheel: zoom out/in at the mouse cursor\n  arrow keys/WASD, middle mouse button drag: pan\n  F: fit grid to window\n  C: toggle population chart\n  H: toggle status and legend\n  I: toggle cell inspector\n  space: toggle pause/resume\n  N: advance one tick while paused\n  G <tick> Enter: run until tick\n  J <tick> Enter: fast-forward to tick\n  Escape: stop running to tick\n  Ctrl+S: save checkpoint\n  Ctrl+L: load checkpoint\n  1-9: select species to paint\n  [/]: decrease/increase brush size\n  left/right mouse button: paint/erase cells\n");
+    gui::main_loop(
+        config_path,
+        stats_path,
+        resume_path,
+        checkpoint_path.unwrap_or(DEFAULT_CHECKPOINT_PATH),
+        random_seed,
+    );
+}
+
+#[cfg(not(feature = "gui"))]
+fn run_gui(
+    _config_path: &str,
+    _stats_path: Option<&str>,
+    _resume_path: Option<&str>,
+    _checkpoint_path: Option<&str>,
+    _random_seed: Option<u64>,
+) {
+    println!("This program was built without the gui feature, use --headless or --sweep instead.");
+    std::process::exit(1);
 }
@@ -0,0 +1,346 @@
+use crate::models::params::{CellMatcher, CellOutcome, NeighborQuantity, TransitionRule};
+use crate::models::*;
+use crate::util::PRng;
+use rand::{Rng, SeedableRng};
+
+/// Neighbor counts (and the most prevalent specie of each kind) around a cell.
+struct NeighborCounts {
+    n_predators: u32,
+    dominant_predator_id: u32,
+    n_prey: u32,
+    dominant_prey_id: u32,
+    n_herbivores: u32,
+    dominant_herbivore_id: u32,
+    n_empty: u32,
+}
+
+impl NeighborCounts {
+    fn get(&self, quantity: NeighborQuantity) -> u32 {
+        match quantity {
+            NeighborQuantity::Predators => self.n_predators,
+            NeighborQuantity::Prey => self.n_prey,
+            NeighborQuantity::Herbivores => self.n_herbivores,
+            NeighborQuantity::Empty => self.n_empty,
+        }
+    }
+}
+
+/// Returns the probability of every outcome of the rule, given the neighbor counts around the cell.
+/// Per-neighbor probabilities can add up to more than 1.0, in which case they are all scaled down to add up to 1.0.
+fn outcome_probabilities(rule: &TransitionRule, counts: &NeighborCounts) -> Vec<f32> {
+    let probabilities: Vec<f32> = rule
+        .outcomes
+        .iter()
+        .map(|outcome| match outcome.per_neighbor {
+            Some(quantity) => {
+                1.0f32 - (1.0f32 - outcome.probability).powf(counts.get(quantity) as f32)
+            }
+            None => outcome.probability,
+        })
+        .collect();
+    let total_probability: f32 = probabilities.iter().sum();
+
+    if total_probability > 1.0 {
+        probabilities
+            .iter()
+            .map(|probability| probability / total_probability)
+            .collect()
+    } else {
+        probabilities
+    }
+}
+
+/// Model whose transition rules are defined in the config file.
+/// Each tick, every cell is updated simultaneously by the first rule that applies to it.
+pub struct CustomModel {
+    grid: Grid,
+    params: ModelParams,
+    rng: PRng,
+    seed: u64, // Seed that the random number generator was created with
+    neighborhood: Neighborhood,
+}
+
+impl CustomModel {
+    pub fn new(params: ModelParams) -> Result<CustomModel, Box<dyn Error>> {
+        let grid = Grid::from_params(&params)?;
+        let neighborhood = Neighborhood::new(
+            params.topology,
+            params
+                .neighborhoods
+                .interaction
+                .as_ref()
+                .unwrap_or(&NeighborhoodParams::moore(1)),
+        );
+        let seed = params.random_seed.unwrap_or(time_ns() as u64);
+        let rng = PRng::seed_from_u64(seed);
+
+        Ok(CustomModel {
+            grid,
+            params,
+            rng,
+            seed,
+            neighborhood,
+        })
+    }
+
+    fn rule_matches(&self, rule: &TransitionRule, cell: &Cell, counts: &NeighborCounts) -> bool {
+        let cell_matches = match (&rule.from, cell) {
+            (CellMatcher::Any, _) => true,
+            (CellMatcher::Empty, Cell::Empty) => true,
+            (CellMatcher::Herbivore, &Cell::Animal(specie_id, _)) => {
+                self.params.is_specie_herbivore(specie_id)
+            }
+            (CellMatcher::Predator, &Cell::Animal(specie_id, _)) => {
+                !self.params.is_specie_herbivore(specie_id)
+            }
+            (CellMatcher::Specie(specie_name), &Cell::Animal(specie_id, _)) => {
+                self.params.specie_id_from_name(specie_name) == specie_id
+            }
+            _ => false,
+        };
+
+        cell_matches
+            && rule.conditions.iter().all(|condition| {
+                let n = counts.get(condition.neighbors);
+                condition.min.map(|min| n >= min).unwrap_or(true)
+                    && condition.max.map(|max| n <= max).unwrap_or(true)
+            })
+    }
+
+    /// Determines the next state of the given cell, given the current state and the cell's surrounding neighbors.
+    fn next_cell_state(&self, cell: &Cell, neighbors: &[Cell], rng: &mut PRng) -> Cell {
+        let (n_predators, dominant_predator_id) =
+            get_neighbor_predators(cell, neighbors, &self.params, rng);
+        let (n_prey, dominant_prey_id) = get_neighbor_prey(cell, neighbors, &self.params, rng);
+        let (n_herbivores, dominant_herbivore_id) =
+            get_neighbor_herbivores(neighbors, &self.params, rng);
+        let n_empty = neighbors
+            .iter()
+            .filter(|neighbor| **neighbor == Cell::Empty)
+            .count() as u32;
+        let counts = NeighborCounts {
+            n_predators,
+            dominant_predator_id,
+            n_prey,
+            dominant_prey_id,
+            n_herbivores,
+            dominant_herbivore_id,
+            n_empty,
+        };
+
+        let rule = match self
+            .params
+            .rules
+            .iter()
+            .find(|rule| self.rule_matches(rule, cell, &counts))
+        {
+            Some(rule) => rule,
+            None => return cell.clone(), // No rule applies, cell remains the same
+        };
+
+        // Pick at most one outcome, each with its own probability. With the remaining probability, the cell remains the same.
+        let random = rng.gen::<f32>();
+        let mut cumulative_probability = 0.0f32;
+        let probabilities = outcome_probabilities(rule, &counts);
+        for (outcome, probability) in rule.outcomes.iter().zip(probabilities) {
+            cumulative_probability += probability;
+
+            if random < cumulative_probability {
+                return match &outcome.to {
+                    CellOutcome::Empty => Cell::Empty,
+                    CellOutcome::Specie(specie_name) => {
+                        let specie_id = self.params.specie_id_from_name(specie_name);
+                        match cell {
+                            // An animal that stays the same specie keeps its state
+                            &Cell::Animal(cell_specie_id, _) if cell_specie_id == specie_id => {
+                                cell.clone()
+                            }
+                            _ => newborn_animal(specie_id, &self.params),
+                        }
+                    }
+                    CellOutcome::DominantPredator if counts.n_predators > 0 => {
+                        newborn_animal(counts.dominant_predator_id, &self.params)
+                    }
+                    CellOutcome::DominantPrey if counts.n_prey > 0 => {
+                        newborn_animal(counts.dominant_prey_id, &self.params)
+                    }
+                    CellOutcome::DominantHerbivore if counts.n_herbivores > 0 => {
+                        newborn_animal(counts.dominant_herbivore_id, &self.params)
+                    }
+                    _ => cell.clone(), // No such neighbor, cell remains the same
+                };
+            }
+        }
+
+        cell.clone()
+    }
+}
+
+impl Model for CustomModel {
+    fn populate(&mut self) -> Result<(), Box<dyn Error>> {
+        self.grid.populate(&self.params, &mut self.rng)
+    }
+
+    fn tick(&mut self) {
+        let tick_seed = self.rng.gen::<u64>();
+        let model = &*self;
+
+        let new_cells = map_cells(
+            self.grid.get_size(),
+            tick_seed,
+            thread_count(self.params.threads),
+            |x, y, rng| {
+                let cell = model.grid.get_cell_at(x, y).clone();
+                let neighbors = model.grid.neighborhood(x, y, &model.neighborhood, None);
+
+                model.next_cell_state(&cell, &neighbors, rng)
+            },
+        );
+
+        self.grid = self.grid.with_cells(new_cells);
+    }
+
+    fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    fn get_grid_mut(&mut self) -> &mut Grid {
+        &mut self.grid
+    }
+
+    fn get_params(&self) -> &ModelParams {
+        &self.params
+    }
+
+    fn get_rng(&self) -> &PRng {
+        &self.rng
+    }
+
+    fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn inspect_cell(&self, x: u32, y: u32) -> Vec<String> {
+        let neighbors = self.grid.neighborhood(x, y, &self.neighborhood, None);
+
+        describe_cell(
+            &self.grid,
+            &self.params,
+            x,
+            y,
+            &[("Interaction", &neighbors)],
+        )
+    }
+
+    fn restore(&mut self, grid: Grid, rng: PRng, _ticks_elapsed: usize) {
+        self.grid = grid;
+        self.rng = rng;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::params::params_from_str;
+    use rand::SeedableRng;
+
+    fn counts(n_predators: u32, n_empty: u32) -> NeighborCounts {
+        NeighborCounts {
+            n_predators,
+            dominant_predator_id: 1,
+            n_prey: 0,
+            dominant_prey_id: 0,
+            n_herbivores: 0,
+            dominant_herbivore_id: 0,
+            n_empty,
+        }
+    }
+
+    fn rule(json: &str) -> TransitionRule {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn outcome_probabilities_below_one_are_kept() {
+        let rule = rule(
+            r#"{
+                "from": "Any",
+                "outcomes": [
+                    { "to": "Empty", "probability": 0.2 },
+                    { "to": "DominantPredator", "probability": 0.5, "per_neighbor": "Predators" }
+                ]
+            }"#,
+        );
+
+        let probabilities = outcome_probabilities(&rule, &counts(1, 0));
+        assert!((probabilities[0] - 0.2).abs() < 1e-6);
+        assert!((probabilities[1] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn per_neighbor_probabilities_above_one_are_normalised() {
+        // 1 - (1 - 0.5)^3 = 0.875 and 1 - (1 - 0.5)^2 = 0.75 add up to 1.625
+        let rule = rule(
+            r#"{
+                "from": "Any",
+                "outcomes": [
+                    { "to": "DominantPredator", "probability": 0.5, "per_neighbor": "Predators" },
+                    { "to": "Empty", "probability": 0.5, "per_neighbor": "Empty" }
+                ]
+            }"#,
+        );
+
+        let probabilities = outcome_probabilities(&rule, &counts(3, 2));
+        assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        assert!((probabilities[0] - 0.875 / 1.625).abs() < 1e-6);
+        assert!((probabilities[1] - 0.75 / 1.625).abs() < 1e-6);
+    }
+
+    /// Returns the fraction of 10000 updates of a lone Fish cell that leave it unchanged, given the outcomes of its only rule.
+    fn unchanged_fraction(outcomes: &str) -> f32 {
+        let params = params_from_str(&format!(
+            r#"{{
+                "model": "Custom",
+                "grid_size": {{ "w": 4, "h": 4 }},
+                "species": {{
+                    "Fish": {{ "initial_population": 0.0, "death_rate": 0.0, "birth_rate": 0.0 }}
+                }},
+                "rules": [{{ "from": "Any", "outcomes": {} }}]
+            }}"#,
+            outcomes
+        ))
+        .unwrap();
+        let model = CustomModel::new(params).unwrap();
+        let cell = newborn_animal(1, &model.params);
+        let mut rng = PRng::seed_from_u64(1);
+
+        let n_unchanged = (0..10000)
+            .filter(|_| model.next_cell_state(&cell, &[], &mut rng) == cell)
+            .count();
+
+        n_unchanged as f32 / 10000.0
+    }
+
+    #[test]
+    fn remaining_probability_leaves_cell_unchanged() {
+        // 0.2 + 0.1 add up to 0.3, so the cell remains the same with probability 0.7
+        let fraction = unchanged_fraction(
+            r#"[{ "to": "Empty", "probability": 0.2 }, { "to": "Empty", "probability": 0.1 }]"#,
+        );
+        assert!((fraction - 0.7).abs() < 0.02, "{}", fraction);
+    }
+
+    #[test]
+    fn zero_probabilities_leave_cell_unchanged() {
+        let fraction = unchanged_fraction(r#"[{ "to": "Empty", "probability": 0.0 }]"#);
+        assert_eq!(fraction, 1.0);
+    }
+
+    #[test]
+    fn probabilities_adding_up_to_one_always_change_cell() {
+        let fraction = unchanged_fraction(
+            r#"[{ "to": "Empty", "probability": 0.4 }, { "to": "Empty", "probability": 0.6 }]"#,
+        );
+        assert_eq!(fraction, 0.0);
+    }
+}
@@ -0,0 +1,354 @@
+use crate::models::*;
+use crate::util::PRng;
+use rand::{Rng, SeedableRng};
+
+/// Asynchronous variant of the predator-prey automaton.
+/// Instead of computing the next state of every cell simultaneously, cells are picked at random and updated in place.
+/// Each tick performs as many updates as there are cells in the grid, so every cell is updated once per tick on average.
+pub struct DSAMModel {
+    grid: Grid,
+    params: ModelParams,
+    rng: PRng,
+    seed: u64, // Seed that the random number generator was created with
+    neighborhood: Neighborhood,
+    rates: Rates,
+    ticks_elapsed: usize, // Number of ticks since the model was created, used to evaluate the rate schedules
+}
+
+impl DSAMModel {
+    pub fn new(params: ModelParams) -> Result<DSAMModel, Box<dyn Error>> {
+        let grid = Grid::from_params(&params)?;
+        let neighborhood = Neighborhood::new(
+            params.topology,
+            params
+                .neighborhoods
+                .interaction
+                .as_ref()
+                .unwrap_or(&NeighborhoodParams::von_neumann(1)),
+        );
+        let seed = params.random_seed.unwrap_or(time_ns() as u64);
+        let rng = PRng::seed_from_u64(seed);
+        let rates = Rates::from_params(&params)?;
+
+        Ok(DSAMModel {
+            grid,
+            params,
+            rng,
+            seed,
+            neighborhood,
+            rates,
+            ticks_elapsed: 0,
+        })
+    }
+
+    /// Returns whether an animal of the given specie may move to the given neighbor.
+    /// Neighbors beyond the grid's edges can always be moved to, in which case the animal leaves the grid.
+    fn can_move_to(&self, neighbor: &Neighbor, specie_id: u32) -> bool {
+        match neighbor {
+            &Neighbor::Inside(x, y) => self.grid.can_occupy(x, y, specie_id),
+            Neighbor::Outside(_) => true,
+        }
+    }
+
+    /// Updates the cell at (x, y) in place by letting it interact with a random neighbor.
+    fn update_cell(&mut self, x: u32, y: u32) {
+        let (specie_id, state) = match *self.grid.get_cell_at(x, y) {
+            Cell::Animal(specie_id, state) => (specie_id, state),
+            Cell::Empty => return,
+        };
+        let specie_params = self.params.get_specie_by_id(specie_id);
+        let death_rate = self.rates.death_rate(specie_id, x, y);
+        let specie_uses_energy = specie_params.uses_energy();
+        let specie_is_herbivore = self.params.is_specie_herbivore(specie_id);
+
+        if !specie_is_herbivore && !specie_uses_energy {
+            // Cell is a predator, which may die of natural causes
+            let random = self.rng.gen::<f32>();
+            if random < death_rate {
+                self.grid.set_cell_at(x, y, Cell::Empty);
+                return;
+            }
+        }
+
+        // Position of the animal after interacting, None if it has left the grid
+        let mut position = Some((x, y));
+        let mut has_eaten = specie_is_herbivore;
+
+        // Neighbors beyond the grid's edges can be interacted with, but are not affected
+        let neighbors = self.grid.neighbors(x, y, &self.neighborhood, None);
+        if !neighbors.is_empty() {
+            let neighbor = neighbors[self.rng.gen_range(0, neighbors.len())].clone();
+            // Births into and deaths in the neighbor's cell use the rates at that cell.
+            // A neighbor beyond the grid's edges has no position, so the rates at the animal's own cell apply instead.
+            let (neighbor_x, neighbor_y) = match neighbor {
+                Neighbor::Inside(neighbor_x, neighbor_y) => (neighbor_x, neighbor_y),
+                Neighbor::Outside(_) => (x, y),
+            };
+            let birth_rate = self.rates.birth_rate(specie_id, neighbor_x, neighbor_y);
+
+            match self.grid.get_neighbor_cell(&neighbor) {
+                Cell::Empty => {
+                    if specie_is_herbivore {
+                        // Herbivore may breed into the empty cell
+                        let random = self.rng.gen::<f32>();
+                        if random < birth_rate {
+                            self.grid.set_neighbor_cell(
+                                &neighbor,
+                                newborn_animal(specie_id, &self.params),
+                            );
+                        }
+                    } else if self.can_move_to(&neighbor, specie_id) {
+                        // Predator moves into the empty cell
+                        self.grid.set_cell_at(x, y, Cell::Empty);
+                        self.grid
+                            .set_neighbor_cell(&neighbor, Cell::Animal(specie_id, state));
+                        position = match neighbor {
+                            Neighbor::Inside(neighbor_x, neighbor_y) => {
+                                Some((neighbor_x, neighbor_y))
+                            }
+                            Neighbor::Outside(_) => None,
+                        };
+                    }
+                }
+                Cell::Animal(neighbor_specie_id, _) => {
+                    if self
+                        .params
+                        .is_specie_predator_for(specie_id, neighbor_specie_id)
+                    {
+                        let prey_death_rate =
+                            self.rates
+                                .death_rate(neighbor_specie_id, neighbor_x, neighbor_y);
+                        let random_1 = self.rng.gen::<f32>();
+
+                        if random_1 < prey_death_rate {
+                            // Hunt succeeded, the predator may breed into the prey's cell
+                            has_eaten = true;
+                            let random_2 = self.rng.gen::<f32>();
+                            let new_cell = if random_2 < birth_rate {
+                                newborn_animal(specie_id, &self.params)
+                            } else {
+                                Cell::Empty
+                            };
+                            self.grid.set_neighbor_cell(&neighbor, new_cell);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some((x, y)) = position {
+            let new_cell = match live_one_step(specie_id, state, has_eaten, &self.params) {
+                Some(state) => Cell::Animal(specie_id, state),
+                // The animal dies of starvation or old age
+                None => Cell::Empty,
+            };
+            self.grid.set_cell_at(x, y, new_cell);
+        }
+    }
+}
+
+impl Model for DSAMModel {
+    fn populate(&mut self) -> Result<(), Box<dyn Error>> {
+        self.grid.populate(&self.params, &mut self.rng)
+    }
+
+    fn tick(&mut self) {
+        self.rates.update(self.ticks_elapsed);
+        let grid_size = self.grid.get_size();
+
+        for _ in 0..(grid_size.w * grid_size.h) {
+            let x = self.rng.gen_range(0, grid_size.w);
+            let y = self.rng.gen_range(0, grid_size.h);
+
+            self.update_cell(x, y);
+        }
+
+        self.ticks_elapsed += 1;
+    }
+
+    fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    fn get_grid_mut(&mut self) -> &mut Grid {
+        &mut self.grid
+    }
+
+    fn get_params(&self) -> &ModelParams {
+        &self.params
+    }
+
+    fn get_rng(&self) -> &PRng {
+        &self.rng
+    }
+
+    fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn inspect_cell(&self, x: u32, y: u32) -> Vec<String> {
+        let neighbors = self.grid.neighborhood(x, y, &self.neighborhood, None);
+
+        describe_cell(
+            &self.grid,
+            &self.params,
+            x,
+            y,
+            &[("Interaction", &neighbors)],
+        )
+    }
+
+    fn restore(&mut self, grid: Grid, rng: PRng, ticks_elapsed: usize) {
+        self.grid = grid;
+        self.rng = rng;
+        self.ticks_elapsed = ticks_elapsed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::params::params_from_str;
+
+    /// Returns a model on an empty 2x1 grid with an herbivore (Algae) and a predator (Shark),
+    /// with the given extra parameters for each specie.
+    fn two_cell_model(algae_fields: &str, shark_fields: &str) -> DSAMModel {
+        let params = params_from_str(&format!(
+            r#"{{
+                "model": "DSAM",
+                "random_seed": 1,
+                "grid_size": {{ "w": 2, "h": 1 }},
+                "species": {{
+                    "Algae": {{ "initial_population": 0.0, {} }},
+                    "Shark": {{ "initial_population": 0.0, "energy_sources": ["Algae"], {} }}
+                }}
+            }}"#,
+            algae_fields, shark_fields
+        ))
+        .unwrap();
+
+        DSAMModel::new(params).unwrap()
+    }
+
+    /// Returns the specie ID at the given cell, or 0 if the cell is empty.
+    fn specie_at(model: &DSAMModel, x: u32, y: u32) -> u32 {
+        match *model.grid.get_cell_at(x, y) {
+            Cell::Animal(specie_id, _) => specie_id,
+            Cell::Empty => 0,
+        }
+    }
+
+    /// Places a newborn animal of the given specie at the given cell.
+    fn place(model: &mut DSAMModel, specie_name: &str, x: u32, y: u32) -> u32 {
+        let specie_id = model.params.specie_id_from_name(specie_name);
+        let cell = newborn_animal(specie_id, &model.params);
+        model.grid.set_cell_at(x, y, cell);
+
+        specie_id
+    }
+
+    #[test]
+    fn predator_moves_into_empty_cell() {
+        let mut model = two_cell_model(
+            r#""death_rate": 0.0, "birth_rate": 0.0"#,
+            r#""death_rate": 0.0, "birth_rate": 0.0"#,
+        );
+        let shark_id = place(&mut model, "Shark", 0, 0);
+
+        model.update_cell(0, 0);
+
+        assert_eq!(specie_at(&model, 0, 0), 0);
+        assert_eq!(specie_at(&model, 1, 0), shark_id);
+    }
+
+    #[test]
+    fn predator_eats_prey_and_breeds_into_its_cell() {
+        let mut model = two_cell_model(
+            r#""death_rate": 1.0, "birth_rate": 0.0"#,
+            r#""death_rate": 0.0, "birth_rate": 1.0"#,
+        );
+        let shark_id = place(&mut model, "Shark", 0, 0);
+        place(&mut model, "Algae", 1, 0);
+
+        model.update_cell(0, 0);
+
+        assert_eq!(specie_at(&model, 0, 0), shark_id);
+        assert_eq!(specie_at(&model, 1, 0), shark_id);
+    }
+
+    #[test]
+    fn predator_eats_prey_without_breeding() {
+        let mut model = two_cell_model(
+            r#""death_rate": 1.0, "birth_rate": 0.0"#,
+            r#""death_rate": 0.0, "birth_rate": 0.0"#,
+        );
+        let shark_id = place(&mut model, "Shark", 0, 0);
+        place(&mut model, "Algae", 1, 0);
+
+        model.update_cell(0, 0);
+
+        assert_eq!(specie_at(&model, 0, 0), shark_id);
+        assert_eq!(specie_at(&model, 1, 0), 0);
+    }
+
+    #[test]
+    fn herbivore_reproduces_into_empty_cell() {
+        let mut model = two_cell_model(
+            r#""death_rate": 0.0, "birth_rate": 1.0"#,
+            r#""death_rate": 0.0, "birth_rate": 0.0"#,
+        );
+        let algae_id = place(&mut model, "Algae", 0, 0);
+
+        model.update_cell(0, 0);
+
+        assert_eq!(specie_at(&model, 0, 0), algae_id);
+        assert_eq!(specie_at(&model, 1, 0), algae_id);
+    }
+
+    #[test]
+    fn predator_dies_by_its_death_rate() {
+        let mut model = two_cell_model(
+            r#""death_rate": 0.0, "birth_rate": 0.0"#,
+            r#""death_rate": 1.0, "birth_rate": 0.0"#,
+        );
+        place(&mut model, "Shark", 0, 0);
+
+        model.update_cell(0, 0);
+
+        assert_eq!(specie_at(&model, 0, 0), 0);
+        assert_eq!(specie_at(&model, 1, 0), 0);
+    }
+
+    #[test]
+    fn predator_with_energy_starves_without_prey() {
+        let mut model = two_cell_model(
+            r#""death_rate": 0.0, "birth_rate": 0.0"#,
+            r#""death_rate": 1.0, "birth_rate": 0.0, "metabolic_cost": 1.0, "initial_energy": 1.5"#,
+        );
+        let shark_id = place(&mut model, "Shark", 0, 0);
+
+        // The death rate does not apply to species that use energy, so the predator survives the first step
+        model.update_cell(0, 0);
+        assert_eq!(specie_at(&model, 1, 0), shark_id);
+
+        model.update_cell(1, 0);
+        assert_eq!(specie_at(&model, 0, 0), 0);
+        assert_eq!(specie_at(&model, 1, 0), 0);
+    }
+
+    #[test]
+    fn tick_updates_cells_in_place() {
+        let mut model = two_cell_model(
+            r#""death_rate": 0.0, "birth_rate": 1.0"#,
+            r#""death_rate": 0.0, "birth_rate": 0.0"#,
+        );
+        let algae_id = place(&mut model, "Algae", 0, 0);
+
+        for _ in 0..5 {
+            model.tick();
+        }
+
+        assert_eq!(specie_at(&model, 0, 0), algae_id);
+        assert_eq!(specie_at(&model, 1, 0), algae_id);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::models::parallel::stream_seed;
+use crate::models::params::{EventAction, EventParams, ModelParams};
+use crate::models::{newborn_animal, Cell, Grid, Model};
+use crate::util::PRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+/// Returns the coordinates of the cells within the given region, or of all cells if no region is given.
+fn region_cells(region_name: Option<&String>, params: &ModelParams) -> Vec<(u32, u32)> {
+    let size = params.grid_size;
+    let (x_range, y_range) = match region_name {
+        Some(region_name) => params.regions[region_name].cell_ranges(size),
+        None => (0..size.w, 0..size.h),
+    };
+
+    y_range
+        .flat_map(|y| x_range.clone().map(move |x| (x, y)))
+        .collect()
+}
+
+/// Applies the action to the grid and returns a description of what happened.
+fn apply_action(
+    action: &EventAction,
+    grid: &mut Grid,
+    params: &ModelParams,
+    rng: &mut PRng,
+) -> String {
+    let in_region = |region_name: Option<&String>| match region_name {
+        Some(region_name) => format!(" in {}", region_name),
+        None => String::new(),
+    };
+
+    match action {
+        EventAction::Cull {
+            specie,
+            fraction,
+            region,
+        } => {
+            let specie_id = params.specie_id_from_name(specie);
+            let cells: Vec<(u32, u32)> = region_cells(region.as_ref(), params)
+                .into_iter()
+                .filter(|&(x, y)| match grid.get_cell_at(x, y) {
+                    &Cell::Animal(cell_specie_id, _) => cell_specie_id == specie_id,
+                    Cell::Empty => false,
+                })
+                .collect();
+            let n_culled = (cells.len() as f32 * fraction).round() as usize;
+
+            for &(x, y) in cells.choose_multiple(rng, n_culled) {
+                grid.set_cell_at(x, y, Cell::Empty);
+            }
+
+            format!(
+                "culled {} of {} {}{}",
+                n_culled,
+                cells.len(),
+                specie,
+                in_region(region.as_ref())
+            )
+        }
+        EventAction::Introduce {
+            specie,
+            count,
+            region,
+        } => {
+            let specie_id = params.specie_id_from_name(specie);
+            let cells: Vec<(u32, u32)> = region_cells(region.as_ref(), params)
+                .into_iter()
+                .filter(|&(x, y)| {
+                    *grid.get_cell_at(x, y) == Cell::Empty && grid.can_occupy(x, y, specie_id)
+                })
+                .collect();
+            // Limited by the number of empty cells
+            let n_introduced = (*count).min(cells.len());
+
+            for &(x, y) in cells.choose_multiple(rng, n_introduced) {
+                grid.set_cell_at(x, y, newborn_animal(specie_id, params));
+            }
+
+            format!(
+                "introduced {} {}{}",
+                n_introduced,
+                specie,
+                in_region(region.as_ref())
+            )
+        }
+        EventAction::Clear { region } => {
+            let mut n_removed = 0;
+
+            for (x, y) in region_cells(Some(region), params) {
+                if *grid.get_cell_at(x, y) != Cell::Empty {
+                    grid.set_cell_at(x, y, Cell::Empty);
+                    n_removed += 1;
+                }
+            }
+
+            format!("cleared {} (removed {} animals)", region, n_removed)
+        }
+    }
+}
+
+/// Applies the events that take place right after the given tick to the model's grid.
+/// Returns a description of every event that took place.
+pub fn apply_events(model: &mut Box<dyn Model>, ticks_elapsed: usize) -> Vec<String> {
+    let events: Vec<EventParams> = model
+        .get_params()
+        .events
+        .iter()
+        .filter(|event| event.tick == ticks_elapsed)
+        .cloned()
+        .collect();
+    if events.is_empty() {
+        return vec![];
+    }
+
+    // Events draw from their own random stream, so that they do not change the model's random state
+    let params = model.get_params().clone();
+    let mut rng = PRng::seed_from_u64(stream_seed(model.get_seed(), ticks_elapsed as u64));
+    let grid = model.get_grid_mut();
+
+    events
+        .iter()
+        .map(|event| apply_action(&event.action, grid, &params, &mut rng))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::create_model_from_str;
+    use rand::Rng;
+
+    /// Returns a populated model of a 10x10 grid with the given initial population of Fish, and the given events.
+    fn model_with_events(initial_population: f32, events: &str) -> Box<dyn Model> {
+        create_model_from_str(&format!(
+            r#"{{
+                "model": "Simple",
+                "random_seed": 3,
+                "grid_size": {{ "w": 10, "h": 10 }},
+                "species": {{
+                    "Fish": {{ "initial_population": {}, "death_rate": 0.0, "birth_rate": 0.0 }}
+                }},
+                "regions": {{ "West": {{ "x": 0.0, "y": 0.0, "w": 0.5, "h": 1.0 }} }},
+                "events": {}
+            }}"#,
+            initial_population, events
+        ))
+        .unwrap()
+    }
+
+    /// Returns the number of animals in the model's grid, optionally only in the west half.
+    fn count_animals(model: &dyn Model, west_only: bool) -> usize {
+        let grid = model.get_grid();
+        let size = grid.get_size();
+        let w = if west_only { size.w / 2 } else { size.w };
+
+        (0..size.h)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .filter(|&(x, y)| *grid.get_cell_at(x, y) != Cell::Empty)
+            .count()
+    }
+
+    #[test]
+    fn event_fires_exactly_at_its_tick() {
+        let mut model = model_with_events(
+            1.0,
+            r#"[{ "tick": 3, "action": { "Cull": { "specie": "Fish", "fraction": 1.0 } } }]"#,
+        );
+
+        for tick in [1, 2, 4].iter() {
+            assert!(apply_events(&mut model, *tick).is_empty());
+            assert_eq!(count_animals(model.as_ref(), false), 100);
+        }
+
+        let descriptions = apply_events(&mut model, 3);
+        assert_eq!(descriptions, vec!["culled 100 of 100 Fish".to_string()]);
+        assert_eq!(count_animals(model.as_ref(), false), 0);
+    }
+
+    #[test]
+    fn cull_removes_fraction_within_region() {
+        let mut model = model_with_events(
+            1.0,
+            r#"[{ "tick": 1, "action": { "Cull": { "specie": "Fish", "fraction": 0.5, "region": "West" } } }]"#,
+        );
+
+        apply_events(&mut model, 1);
+
+        assert_eq!(count_animals(model.as_ref(), true), 25);
+        assert_eq!(count_animals(model.as_ref(), false), 75);
+    }
+
+    #[test]
+    fn introduce_places_count_within_region() {
+        let mut model = model_with_events(
+            0.0,
+            r#"[{ "tick": 1, "action": { "Introduce": { "specie": "Fish", "count": 7, "region": "West" } } }]"#,
+        );
+
+        let descriptions = apply_events(&mut model, 1);
+
+        assert_eq!(descriptions, vec!["introduced 7 Fish in West".to_string()]);
+        assert_eq!(count_animals(model.as_ref(), true), 7);
+        assert_eq!(count_animals(model.as_ref(), false), 7);
+    }
+
+    #[test]
+    fn clear_empties_region() {
+        let mut model = model_with_events(
+            1.0,
+            r#"[{ "tick": 2, "action": { "Clear": { "region": "West" } } }]"#,
+        );
+
+        apply_events(&mut model, 2);
+
+        assert_eq!(count_animals(model.as_ref(), true), 0);
+        assert_eq!(count_animals(model.as_ref(), false), 50);
+    }
+
+    #[test]
+    fn events_do_not_change_model_random_state() {
+        let mut model = model_with_events(
+            1.0,
+            r#"[{ "tick": 1, "action": { "Cull": { "specie": "Fish", "fraction": 0.5 } } }]"#,
+        );
+        let mut rng_before = model.get_rng().clone();
+
+        apply_events(&mut model, 1);
+
+        let mut rng_after = model.get_rng().clone();
+        assert_eq!(rng_after.gen::<u64>(), rng_before.gen::<u64>());
+    }
+}
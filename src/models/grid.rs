@@ -1,123 +1,446 @@
-use crate::util::{time_ns, PRng, Size};
-use rand::{Rng, SeedableRng};
+use crate::util::{PRng, Size};
+use rand::Rng;
 
-use crate::models::params::{ModelParams, ModelType};
+use crate::models::neighborhood::Neighborhood;
+use crate::models::params::{ModelParams, Topology};
+use crate::models::placement::placement_cells;
+use crate::models::terrain::Terrain;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
 
-#[derive(Clone, PartialEq)]
+/// Per-individual state of an animal. Only changes over time for species with energy or lifespan parameters.
+#[derive(Copy, Clone, PartialEq, Default, Debug, Serialize, Deserialize)]
+pub struct AnimalState {
+    pub energy: f32,
+    pub age: u32, // Age in time steps
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub enum Cell {
     Empty,
-    Animal(u32),
+    Animal(u32, AnimalState),
+}
+
+/// Boundary of one edge of the grid, which determines what lies beyond that edge.
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Boundary {
+    Bounded,    // Nothing, cells beyond the edge are missing
+    Periodic,   // The opposite edge of the grid
+    Reflecting, // A mirror image of the cells along the edge
+    Absorbing,  // Empty cells
+    Fixed(u32), // Animals of the given specie
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Boundaries {
+    pub west: Boundary,
+    pub north: Boundary,
+    pub east: Boundary,
+    pub south: Boundary,
 }
 
+/// A neighbor of a cell, either a cell inside the grid or a cell beyond the grid's edge.
 #[derive(Clone)]
+pub enum Neighbor {
+    Inside(u32, u32),
+    Outside(Cell),
+}
+
+/// A coordinate along one axis of the grid, after applying the boundary.
+enum AxisCoord {
+    Inside(u32),
+    Outside(Cell),
+    Missing,
+}
+
+/// Resolves a coordinate along an axis of the given size, with the given boundaries at the low and high end.
+fn resolve_axis_coord(coord: i32, size: u32, low: Boundary, high: Boundary) -> AxisCoord {
+    if coord >= 0 && coord < size as i32 {
+        return AxisCoord::Inside(coord as u32);
+    }
+
+    let boundary = if coord < 0 { low } else { high };
+    match boundary {
+        Boundary::Bounded => AxisCoord::Missing,
+        Boundary::Periodic => AxisCoord::Inside(coord.rem_euclid(size as i32) as u32),
+        // An axis of a single cell has no other cell to mirror
+        Boundary::Reflecting if size == 1 => AxisCoord::Missing,
+        Boundary::Reflecting => {
+            // Mirror about the edge cell, i.e. -1 maps to 1 and size maps to size - 2,
+            // so that a cell on the edge is never its own neighbor
+            let reflected = if coord < 0 {
+                -coord
+            } else {
+                2 * (size as i32 - 1) - coord
+            };
+            AxisCoord::Inside(reflected.clamp(0, size as i32 - 1) as u32)
+        }
+        Boundary::Absorbing => AxisCoord::Outside(Cell::Empty),
+        Boundary::Fixed(specie_id) => {
+            AxisCoord::Outside(Cell::Animal(specie_id, AnimalState::default()))
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Grid {
     size: Size,
+    topology: Topology,
+    boundaries: Boundaries,
+    terrain: Terrain,
     cells: Vec<Cell>,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+/// Direction of a part of a cell's neighborhood.
+/// A square grid uses the four compass directions, a hexagonal grid uses east and west plus the four diagonal directions.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd, Debug)]
 pub enum Quadrant {
     West,
     North,
     East,
     South,
+    NorthEast,
+    NorthWest,
+    SouthWest,
+    SouthEast,
+}
+
+const SQUARE_QUADRANTS: [Quadrant; 4] = [
+    Quadrant::East,
+    Quadrant::North,
+    Quadrant::West,
+    Quadrant::South,
+];
+
+// Directions of a hexagonal cell's six neighbors, with the neighbor's offset (dq, dr) in axial coordinates.
+const HEX_DIRECTIONS: [(Quadrant, i32, i32); 6] = [
+    (Quadrant::East, 1, 0),
+    (Quadrant::NorthEast, 1, -1),
+    (Quadrant::NorthWest, 0, -1),
+    (Quadrant::West, -1, 0),
+    (Quadrant::SouthWest, -1, 1),
+    (Quadrant::SouthEast, 0, 1),
+];
+
+/// Returns the quadrants that a cell's neighborhood is divided into in the given topology.
+pub fn topology_quadrants(topology: Topology) -> Vec<Quadrant> {
+    match topology {
+        Topology::Square => SQUARE_QUADRANTS.to_vec(),
+        Topology::Hexagonal => HEX_DIRECTIONS
+            .iter()
+            .map(|(quadrant, _, _)| *quadrant)
+            .collect(),
+    }
+}
+
+/// Converts an offset (dq, dr) in axial coordinates from the hexagonal cell at (x, y) to an offset (dx, dy) in grid coordinates.
+/// The hexagonal grid is stored in "odd-r" layout, i.e. odd rows are shifted half a cell to the right.
+fn hex_offset(x: u32, y: u32, dq: i32, dr: i32) -> (i32, i32) {
+    let y = y as i32;
+    let q = x as i32 - (y - (y & 1)) / 2;
+    let neighbor_y = y + dr;
+    let neighbor_x = q + dq + (neighbor_y - (neighbor_y & 1)) / 2;
+
+    (neighbor_x - x as i32, dr)
+}
+
+/// Rounds fractional axial coordinates (q, r) to the axial coordinates of the hexagonal cell that contains them.
+fn hex_round(q: f32, r: f32) -> (i32, i32) {
+    let s = -q - r;
+    let (mut rounded_q, mut rounded_r, rounded_s) = (q.round(), r.round(), s.round());
+    let (q_diff, r_diff, s_diff) = (
+        (rounded_q - q).abs(),
+        (rounded_r - r).abs(),
+        (rounded_s - s).abs(),
+    );
+
+    // Restore the constraint q + r + s = 0 by recomputing the coordinate with the largest rounding error
+    if q_diff > r_diff && q_diff > s_diff {
+        rounded_q = -rounded_r - rounded_s;
+    } else if r_diff > s_diff {
+        rounded_r = -rounded_q - rounded_s;
+    }
+
+    (rounded_q as i32, rounded_r as i32)
 }
 
 impl Grid {
-    pub fn new(size: Size) -> Grid {
+    pub fn new(size: Size, topology: Topology, boundaries: Boundaries) -> Grid {
         Grid {
-            size: size,
+            size,
+            topology,
+            boundaries,
+            terrain: Terrain::open(size),
             cells: vec![Cell::Empty; size.w as usize * size.h as usize],
         }
     }
 
-    pub fn populate(&mut self, params: &ModelParams, rng: &mut PRng) {
+    /// Creates an empty grid with the size, topology, boundaries and terrain given in the parameters.
+    /// Returns an error if the initial image cannot be read.
+    pub fn from_params(params: &ModelParams) -> Result<Grid, Box<dyn Error>> {
+        let mut grid = Grid::new(params.grid_size, params.topology, params.grid_boundaries());
+        grid.terrain = Terrain::from_params(params)?;
+
+        Ok(grid)
+    }
+
+    /// Returns a grid with the same size, topology, boundaries and terrain, containing the given cells in row-major order.
+    /// Animals in cells that their specie may not occupy are left out.
+    pub fn with_cells(&self, cells: Vec<Cell>) -> Grid {
+        assert_eq!(cells.len(), self.cells.len());
+
+        let mut grid = Grid {
+            size: self.size,
+            topology: self.topology,
+            boundaries: self.boundaries,
+            terrain: self.terrain.clone(),
+            cells,
+        };
+        for index in 0..grid.cells.len() {
+            if let Cell::Animal(specie_id, _) = grid.cells[index] {
+                if !grid.terrain.allows(index, specie_id) {
+                    grid.cells[index] = Cell::Empty;
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// Fills the grid with the initial population of every specie, either from the initial image,
+    /// or by the specie's placement strategies or at random positions.
+    /// Returns an error if the initial image cannot be read.
+    pub fn populate(&mut self, params: &ModelParams, rng: &mut PRng) -> Result<(), Box<dyn Error>> {
+        if let Some(image) = params.get_initial_image()? {
+            let specie_colors = params.specie_colors();
+
+            for y in 0..self.size.h {
+                for x in 0..self.size.w {
+                    if let Some(specie_id) =
+                        specie_colors.get(&image.color_at_cell(x, y, self.size))
+                    {
+                        let specie_state = params.get_specie_by_id(*specie_id).newborn_state();
+                        self.set_cell_at(x, y, Cell::Animal(*specie_id, specie_state));
+                    }
+                }
+            }
+            return Ok(());
+        }
+
         let specie_ids = params.specie_ids();
 
         for (specie_name, specie_params) in params.species.iter() {
-            let specie_id = specie_ids.get_by_left(&specie_name).unwrap();
+            let specie_id = specie_ids.get_by_left(specie_name).unwrap();
+
+            if let Some(placements) = &specie_params.placement {
+                // Structured placement, cells that are already occupied are skipped
+                for placement in placements.iter() {
+                    for (x, y) in placement_cells(placement, self.size, rng) {
+                        if self.get_cell_at(x, y) == &Cell::Empty {
+                            self.set_cell_at(
+                                x,
+                                y,
+                                Cell::Animal(*specie_id, specie_params.newborn_state()),
+                            );
+                        }
+                    }
+                }
+                continue;
+            }
+
             let target_population =
                 (specie_params.initial_population * self.size.w as f32 * self.size.h as f32) as u32;
+            // The population is limited by the free cells that the specie may occupy
+            let n_free_cells = (0..self.cells.len())
+                .filter(|index| {
+                    self.cells[*index] == Cell::Empty && self.terrain.allows(*index, *specie_id)
+                })
+                .count() as u32;
+            let target_population = target_population.min(n_free_cells);
             let mut population = 0;
             while population < target_population {
                 let new_x = rng.gen_range(0, self.size.w);
                 let new_y = rng.gen_range(0, self.size.h);
 
-                if self.get_cell_at(new_x, new_y) == &Cell::Empty {
-                    self.set_cell_at(new_x, new_y, Cell::Animal(*specie_id));
+                if self.get_cell_at(new_x, new_y) == &Cell::Empty
+                    && self.can_occupy(new_x, new_y, *specie_id)
+                {
+                    self.set_cell_at(
+                        new_x,
+                        new_y,
+                        Cell::Animal(*specie_id, specie_params.newborn_state()),
+                    );
                     population += 1;
                 }
             }
         }
+
+        Ok(())
     }
 
-    /// Calculates the Moore neighborhood M around the cell at (x, y).
-    /// This is also known as the surrounding cells in the square with the given (border) radius.
-    /// A radius of 1 means a square shape of size (3, 3), excluding the middle cell.
+    /// Calculates the neighbors of the cell at (x, y) in the given neighborhood,
+    /// including the neighbors beyond the grid's edges.
     ///
-    /// Optionally filters the neighbors down to a specific quadrant (north, east, west or south).
+    /// Optionally filters the neighbors down to a specific quadrant (north, east, west or south),
+    /// or in a hexagonal grid to one of the six directions.
     /// Note that the bordering cells between quadrants may be included in multiple quadrants.
-    pub fn moore_neighborhood(
+    ///
+    /// Obstacles are not neighbors, and neither are the cells behind them.
+    pub fn neighbors(
         &self,
         x: u32,
         y: u32,
-        radius: u32,
+        neighborhood: &Neighborhood,
         quadrant: Option<Quadrant>,
-    ) -> Vec<Cell> {
-        let mut neighbors = vec![];
+    ) -> Vec<Neighbor> {
+        // All neighbors of a cell far enough from the edges lie inside the grid, so the boundaries need not be applied
+        let reach = neighborhood.get_reach();
+        let is_interior =
+            x >= reach && y >= reach && x + reach < self.size.w && y + reach < self.size.h;
 
-        let i_radius = radius as i32;
-
-        for i in -i_radius..(i_radius + 1) {
-            for j in -i_radius..(i_radius + 1) {
-                if i == 0 && j == 0 {
-                    continue;
-                }
-
-                if (x as i32 + i) >= 0
-                    && (x as i32 + i) < self.size.w as i32
-                    && (y as i32 + j) >= 0
-                    && (y as i32 + j) < self.size.h as i32
-                {
-                    let inside_quadrant = match quadrant {
-                        None => true,
-                        Some(Quadrant::East) => i > 0 && j >= -i && j <= i,
-                        Some(Quadrant::North) => j < 0 && i >= j && i <= -j,
-                        Some(Quadrant::West) => i < 0 && j >= i && j <= -i,
-                        Some(Quadrant::South) => j > 0 && i >= -j && i <= j,
+        neighborhood
+            .get_offsets(quadrant)
+            .iter()
+            .filter_map(|&(i, j)| {
+                let neighbor = if is_interior {
+                    let (dx, dy) = match self.topology {
+                        Topology::Square => (i, j),
+                        Topology::Hexagonal => hex_offset(x, y, i, j),
                     };
-
-                    if inside_quadrant {
-                        neighbors.push(
-                            self.get_cell_at((x as i32 + i) as u32, (y as i32 + j) as u32)
-                                .clone(),
-                        );
+                    Neighbor::Inside((x as i32 + dx) as u32, (y as i32 + dy) as u32)
+                } else {
+                    self.neighbor_at_offset(x, y, i, j)?
+                };
+                if self.terrain.has_obstacles() {
+                    if let Neighbor::Inside(neighbor_x, neighbor_y) = neighbor {
+                        if self.is_obstacle_at(neighbor_x, neighbor_y) {
+                            return None;
+                        }
+                    }
+                    if !self.has_line_of_sight(x, y, i, j) {
+                        return None;
                     }
                 }
+
+                Some(neighbor)
+            })
+            .collect()
+    }
+
+    /// Returns the neighbor at the given neighborhood offset, which is in axial coordinates in a hexagonal grid.
+    fn neighbor_at_offset(&self, x: u32, y: u32, i: i32, j: i32) -> Option<Neighbor> {
+        let (dx, dy) = match self.topology {
+            Topology::Square => (i, j),
+            Topology::Hexagonal => hex_offset(x, y, i, j),
+        };
+
+        self.neighbor_at(x, y, dx, dy)
+    }
+
+    /// Returns whether no obstacles lie on the line between the cell at (x, y) and its neighbor at the given neighborhood offset.
+    fn has_line_of_sight(&self, x: u32, y: u32, i: i32, j: i32) -> bool {
+        let n_steps = match self.topology {
+            Topology::Square => i.abs().max(j.abs()),
+            Topology::Hexagonal => (i.abs() + j.abs() + (i + j).abs()) / 2,
+        };
+
+        (1..n_steps).all(|step| {
+            let t = step as f32 / n_steps as f32;
+            let (step_i, step_j) = match self.topology {
+                Topology::Square => ((i as f32 * t).round() as i32, (j as f32 * t).round() as i32),
+                Topology::Hexagonal => hex_round(i as f32 * t, j as f32 * t),
+            };
+
+            match self.neighbor_at_offset(x, y, step_i, step_j) {
+                Some(Neighbor::Inside(step_x, step_y)) => !self.is_obstacle_at(step_x, step_y),
+                _ => true,
             }
-        }
+        })
+    }
+
+    /// Calculates the cells in the given neighborhood around the cell at (x, y),
+    /// optionally filtered down to a specific quadrant.
+    pub fn neighborhood(
+        &self,
+        x: u32,
+        y: u32,
+        neighborhood: &Neighborhood,
+        quadrant: Option<Quadrant>,
+    ) -> Vec<Cell> {
+        self.neighbors(x, y, neighborhood, quadrant)
+            .iter()
+            .map(|neighbor| self.get_neighbor_cell(neighbor))
+            .collect()
+    }
 
-        neighbors
+    /// Returns the quadrants that a cell's neighborhood is divided into.
+    pub fn quadrants(&self) -> Vec<Quadrant> {
+        topology_quadrants(self.topology)
     }
 
-    /// Calculates the Von Neumann neighborhood around the cell at (x, y) with radius 1.
-    pub fn von_neumann_neighborhood_r1(&self, x: u32, y: u32) -> Vec<Cell> {
-        let mut neighbors = vec![];
+    /// Returns the coordinates of the cell adjacent to the cell at (x, y) in the direction of the given quadrant.
+    /// Returns None if the adjacent cell does not lie inside the grid.
+    pub fn adjacent_coord(&self, x: u32, y: u32, quadrant: Quadrant) -> Option<(u32, u32)> {
+        let (dx, dy) = match self.topology {
+            Topology::Square => match quadrant {
+                Quadrant::West => (-1, 0),
+                Quadrant::North => (0, -1),
+                Quadrant::East => (1, 0),
+                Quadrant::South => (0, 1),
+                _ => return None, // Not used in a square grid
+            },
+            Topology::Hexagonal => {
+                let (_, dq, dr) = HEX_DIRECTIONS
+                    .iter()
+                    .find(|(hex_quadrant, _, _)| *hex_quadrant == quadrant)?;
+                hex_offset(x, y, *dq, *dr)
+            }
+        };
 
-        if x > 0 {
-            neighbors.push(self.get_cell_at(x - 1, y).clone());
-        }
-        if y > 0 {
-            neighbors.push(self.get_cell_at(x, y - 1).clone());
-        }
-        if x < self.size.w - 1 {
-            neighbors.push(self.get_cell_at(x + 1, y).clone());
+        self.neighbor_coord(x, y, dx, dy)
+    }
+
+    /// Returns the neighbor at offset (dx, dy) from the cell at (x, y), taking the boundaries into account.
+    /// Returns None if the neighbor is missing.
+    pub fn neighbor_at(&self, x: u32, y: u32, dx: i32, dy: i32) -> Option<Neighbor> {
+        let neighbor_x = resolve_axis_coord(
+            x as i32 + dx,
+            self.size.w,
+            self.boundaries.west,
+            self.boundaries.east,
+        );
+        let neighbor_y = resolve_axis_coord(
+            y as i32 + dy,
+            self.size.h,
+            self.boundaries.north,
+            self.boundaries.south,
+        );
+
+        match (neighbor_x, neighbor_y) {
+            (AxisCoord::Missing, _) | (_, AxisCoord::Missing) => None,
+            (AxisCoord::Outside(cell), _) | (_, AxisCoord::Outside(cell)) => {
+                Some(Neighbor::Outside(cell))
+            }
+            (AxisCoord::Inside(neighbor_x), AxisCoord::Inside(neighbor_y)) => {
+                Some(Neighbor::Inside(neighbor_x, neighbor_y))
+            }
         }
-        if y < self.size.h - 1 {
-            neighbors.push(self.get_cell_at(x, y + 1).clone());
+    }
+
+    /// Returns the coordinates of the cell at offset (dx, dy) from the cell at (x, y), taking the boundaries into account.
+    /// Returns None if the neighbor does not lie inside the grid.
+    pub fn neighbor_coord(&self, x: u32, y: u32, dx: i32, dy: i32) -> Option<(u32, u32)> {
+        match self.neighbor_at(x, y, dx, dy) {
+            Some(Neighbor::Inside(neighbor_x, neighbor_y)) => Some((neighbor_x, neighbor_y)),
+            _ => None,
         }
+    }
 
-        neighbors
+    pub fn get_neighbor_cell(&self, neighbor: &Neighbor) -> Cell {
+        match neighbor {
+            &Neighbor::Inside(x, y) => self.get_cell_at(x, y).clone(),
+            Neighbor::Outside(cell) => cell.clone(),
+        }
     }
 
     #[inline]
@@ -125,9 +448,41 @@ impl Grid {
         &self.cells[x as usize + y as usize * self.size.w as usize]
     }
 
+    /// Sets the cell at (x, y). An animal is left out if its specie may not occupy the cell.
     #[inline]
     pub fn set_cell_at(&mut self, x: u32, y: u32, cell: Cell) {
-        self.cells[x as usize + y as usize * self.size.w as usize] = cell;
+        let index = x as usize + y as usize * self.size.w as usize;
+        self.cells[index] = match cell {
+            Cell::Animal(specie_id, _) if !self.terrain.allows(index, specie_id) => Cell::Empty,
+            cell => cell,
+        };
+    }
+
+    #[inline]
+    pub fn is_obstacle_at(&self, x: u32, y: u32) -> bool {
+        self.terrain
+            .is_obstacle(x as usize + y as usize * self.size.w as usize)
+    }
+
+    /// Returns whether an animal of the given specie may occupy the cell at (x, y).
+    #[inline]
+    pub fn can_occupy(&self, x: u32, y: u32, specie_id: u32) -> bool {
+        self.terrain
+            .allows(x as usize + y as usize * self.size.w as usize, specie_id)
+    }
+
+    /// Returns the habitat id of the cell at (x, y), 0 for open terrain.
+    #[inline]
+    pub fn get_habitat_id_at(&self, x: u32, y: u32) -> u32 {
+        self.terrain
+            .get_habitat_id(x as usize + y as usize * self.size.w as usize)
+    }
+
+    /// Sets the cell of the given neighbor. Neighbors beyond the grid's edges are not affected.
+    pub fn set_neighbor_cell(&mut self, neighbor: &Neighbor, cell: Cell) {
+        if let &Neighbor::Inside(x, y) = neighbor {
+            self.set_cell_at(x, y, cell);
+        }
     }
 
     pub fn get_cell_specie_ids(&self) -> Vec<u32> {
@@ -135,7 +490,7 @@ impl Grid {
             .iter()
             .map(|cell| match cell {
                 Cell::Empty => 0,
-                Cell::Animal(specie_id) => *specie_id,
+                Cell::Animal(specie_id, _) => *specie_id,
             })
             .collect()
     }
@@ -143,4 +498,297 @@ impl Grid {
     pub const fn get_size(&self) -> Size {
         self.size
     }
+
+    pub const fn get_topology(&self) -> Topology {
+        self.topology
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::params::{NeighborhoodParams, NeighborhoodShape};
+
+    /// Returns the grid coordinate a resolved axis coordinate lies at, if it lies inside the grid.
+    fn inside(coord: AxisCoord) -> Option<u32> {
+        match coord {
+            AxisCoord::Inside(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Returns the specie ID of the cell beyond the edge, or 0 for an empty cell.
+    /// Panics if the coordinate does not lie beyond the edge.
+    fn outside(coord: AxisCoord) -> u32 {
+        match coord {
+            AxisCoord::Outside(Cell::Empty) => 0,
+            AxisCoord::Outside(Cell::Animal(specie_id, state)) => {
+                assert_eq!(state, AnimalState::default());
+                specie_id
+            }
+            _ => panic!("Coordinate does not lie beyond the edge"),
+        }
+    }
+
+    fn all_edges(boundary: Boundary) -> Boundaries {
+        Boundaries {
+            west: boundary,
+            north: boundary,
+            east: boundary,
+            south: boundary,
+        }
+    }
+
+    /// Returns the sorted coordinates of the neighbors of the cell at (x, y) that lie inside the grid.
+    fn neighbor_coords(
+        grid: &Grid,
+        x: u32,
+        y: u32,
+        params: &NeighborhoodParams,
+    ) -> Vec<(u32, u32)> {
+        let neighborhood = Neighborhood::new(grid.get_topology(), params);
+        let mut coords: Vec<(u32, u32)> = grid
+            .neighbors(x, y, &neighborhood, None)
+            .into_iter()
+            .filter_map(|neighbor| match neighbor {
+                Neighbor::Inside(neighbor_x, neighbor_y) => Some((neighbor_x, neighbor_y)),
+                Neighbor::Outside(_) => None,
+            })
+            .collect();
+        coords.sort_unstable();
+
+        coords
+    }
+
+    /// Returns the position of the center of the hexagonal cell at (x, y), with a distance of 1 between neighboring cells.
+    fn hex_center(x: u32, y: u32) -> (f32, f32) {
+        (
+            x as f32 + 0.5 * (y & 1) as f32,
+            y as f32 * 3f32.sqrt() / 2.0,
+        )
+    }
+
+    #[test]
+    fn coordinates_inside_ignore_boundaries() {
+        for coord in 0..5 {
+            let resolved = resolve_axis_coord(coord, 5, Boundary::Bounded, Boundary::Bounded);
+            assert_eq!(inside(resolved), Some(coord as u32));
+        }
+    }
+
+    #[test]
+    fn reflecting_mirrors_about_edge_cell() {
+        let (low, high) = (Boundary::Reflecting, Boundary::Absorbing);
+        assert_eq!(inside(resolve_axis_coord(-1, 5, low, high)), Some(1));
+        assert_eq!(inside(resolve_axis_coord(-2, 5, low, high)), Some(2));
+        assert_eq!(inside(resolve_axis_coord(-9, 5, low, high)), Some(4));
+
+        let (low, high) = (Boundary::Absorbing, Boundary::Reflecting);
+        assert_eq!(inside(resolve_axis_coord(5, 5, low, high)), Some(3));
+        assert_eq!(inside(resolve_axis_coord(6, 5, low, high)), Some(2));
+        assert_eq!(inside(resolve_axis_coord(14, 5, low, high)), Some(0));
+    }
+
+    #[test]
+    fn reflecting_on_single_cell_axis_is_missing() {
+        let boundary = Boundary::Reflecting;
+        assert!(matches!(
+            resolve_axis_coord(-1, 1, boundary, boundary),
+            AxisCoord::Missing
+        ));
+        assert!(matches!(
+            resolve_axis_coord(1, 1, boundary, boundary),
+            AxisCoord::Missing
+        ));
+    }
+
+    #[test]
+    fn absorbing_yields_empty_cells() {
+        let (low, high) = (Boundary::Absorbing, Boundary::Bounded);
+        assert_eq!(outside(resolve_axis_coord(-1, 5, low, high)), 0);
+        assert!(matches!(
+            resolve_axis_coord(5, 5, low, high),
+            AxisCoord::Missing
+        ));
+
+        let (low, high) = (Boundary::Bounded, Boundary::Absorbing);
+        assert_eq!(outside(resolve_axis_coord(5, 5, low, high)), 0);
+        assert!(matches!(
+            resolve_axis_coord(-1, 5, low, high),
+            AxisCoord::Missing
+        ));
+    }
+
+    #[test]
+    fn fixed_yields_animals_of_specie() {
+        let (low, high) = (Boundary::Fixed(1), Boundary::Fixed(2));
+        assert_eq!(outside(resolve_axis_coord(-1, 5, low, high)), 1);
+        assert_eq!(outside(resolve_axis_coord(-3, 5, low, high)), 1);
+        assert_eq!(outside(resolve_axis_coord(5, 5, low, high)), 2);
+        assert_eq!(outside(resolve_axis_coord(7, 5, low, high)), 2);
+    }
+
+    #[test]
+    fn periodic_wraps_to_opposite_edge() {
+        let boundary = Boundary::Periodic;
+        assert_eq!(
+            inside(resolve_axis_coord(-1, 5, boundary, boundary)),
+            Some(4)
+        );
+        assert_eq!(
+            inside(resolve_axis_coord(5, 5, boundary, boundary)),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn neighbor_at_uses_boundary_of_each_edge() {
+        let boundaries = Boundaries {
+            west: Boundary::Reflecting,
+            north: Boundary::Absorbing,
+            east: Boundary::Fixed(3),
+            south: Boundary::Bounded,
+        };
+        let grid = Grid::new(Size::new(4, 4), Topology::Square, boundaries);
+
+        assert!(matches!(
+            grid.neighbor_at(0, 2, -1, 0),
+            Some(Neighbor::Inside(1, 2))
+        ));
+        assert!(matches!(
+            grid.neighbor_at(1, 0, 0, -1),
+            Some(Neighbor::Outside(Cell::Empty))
+        ));
+        assert!(matches!(
+            grid.neighbor_at(3, 1, 1, 0),
+            Some(Neighbor::Outside(Cell::Animal(3, _)))
+        ));
+        assert!(grid.neighbor_at(2, 3, 0, 1).is_none());
+    }
+
+    #[test]
+    fn reflecting_hex_rows_mirror_neighbor_direction() {
+        // Axial offsets of the neighbors of a hexagonal cell
+        let directions = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+
+        // An even and an odd height, so that the last row is both an even and an odd row
+        for &height in &[5, 6] {
+            let grid = Grid::new(
+                Size::new(6, height),
+                Topology::Hexagonal,
+                all_edges(Boundary::Reflecting),
+            );
+
+            for &y in &[0, height - 1] {
+                for x in 1..5 {
+                    for &(dq, dr) in &directions {
+                        let (dx, dy) = hex_offset(x, y, dq, dr);
+                        if (y as i32 + dy) >= 0 && (y as i32 + dy) < height as i32 {
+                            continue;
+                        }
+
+                        // Mirroring a hexagonal direction across a row keeps the row's parity,
+                        // so the reflected cell must be the neighbor in the mirrored direction
+                        let (mirrored_dx, mirrored_dy) = hex_offset(x, y, dq + dr, -dr);
+                        let expected = (
+                            (x as i32 + mirrored_dx) as u32,
+                            (y as i32 + mirrored_dy) as u32,
+                        );
+                        match grid.neighbor_at(x, y, dx, dy) {
+                            Some(Neighbor::Inside(nx, ny)) => assert_eq!((nx, ny), expected),
+                            _ => panic!("Reflected neighbor does not lie inside the grid"),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hex_neighbors_on_even_row() {
+        let grid = Grid::new(
+            Size::new(6, 6),
+            Topology::Hexagonal,
+            all_edges(Boundary::Bounded),
+        );
+        let mut expected = vec![(1, 2), (3, 2), (1, 1), (2, 1), (1, 3), (2, 3)];
+        expected.sort_unstable();
+
+        assert_eq!(
+            neighbor_coords(&grid, 2, 2, &NeighborhoodParams::von_neumann(1)),
+            expected
+        );
+    }
+
+    #[test]
+    fn hex_neighbors_on_odd_row() {
+        let grid = Grid::new(
+            Size::new(6, 6),
+            Topology::Hexagonal,
+            all_edges(Boundary::Bounded),
+        );
+        let mut expected = vec![(1, 3), (3, 3), (2, 2), (3, 2), (2, 4), (3, 4)];
+        expected.sort_unstable();
+
+        assert_eq!(
+            neighbor_coords(&grid, 2, 3, &NeighborhoodParams::von_neumann(1)),
+            expected
+        );
+    }
+
+    #[test]
+    fn hex_neighbors_wrap_across_periodic_edges() {
+        let grid = Grid::new(
+            Size::new(4, 4),
+            Topology::Hexagonal,
+            all_edges(Boundary::Periodic),
+        );
+
+        // Corner cell on an even row, whose neighbors above lie on the last (odd) row
+        let mut expected = vec![(1, 0), (3, 0), (3, 3), (0, 3), (3, 1), (0, 1)];
+        expected.sort_unstable();
+        assert_eq!(
+            neighbor_coords(&grid, 0, 0, &NeighborhoodParams::von_neumann(1)),
+            expected
+        );
+
+        // Cell at the east edge of an odd row, whose neighbors to the east wrap to the first column
+        let mut expected = vec![(0, 1), (2, 1), (3, 0), (0, 0), (3, 2), (0, 2)];
+        expected.sort_unstable();
+        assert_eq!(
+            neighbor_coords(&grid, 3, 1, &NeighborhoodParams::von_neumann(1)),
+            expected
+        );
+    }
+
+    #[test]
+    fn hex_circular_neighborhood_matches_euclidean_distance() {
+        let size = Size::new(9, 9);
+        let grid = Grid::new(size, Topology::Hexagonal, all_edges(Boundary::Bounded));
+
+        for &(x, y) in &[(4, 4), (4, 3)] {
+            for &radius in &[1, 2] {
+                let params = NeighborhoodParams {
+                    shape: NeighborhoodShape::Circular,
+                    radius,
+                };
+                let (center_x, center_y) = hex_center(x, y);
+                let mut expected = vec![];
+                for other_y in 0..size.h {
+                    for other_x in 0..size.w {
+                        let (other_center_x, other_center_y) = hex_center(other_x, other_y);
+                        let distance = ((other_center_x - center_x).powi(2)
+                            + (other_center_y - center_y).powi(2))
+                        .sqrt();
+                        if (other_x, other_y) != (x, y) && distance <= radius as f32 + 1e-3 {
+                            expected.push((other_x, other_y));
+                        }
+                    }
+                }
+                expected.sort_unstable();
+
+                assert_eq!(neighbor_coords(&grid, x, y, &params), expected);
+            }
+        }
+    }
 }
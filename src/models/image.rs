@@ -0,0 +1,280 @@
+use crate::util::Size;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io;
+
+/// An RGB image, used as the initial layout of the grid or as a rate field.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u32>, // Colors in 0xRRGGBB format, in row-major order
+}
+
+impl Image {
+    /// Returns the color of the pixel that covers the cell at (x, y) when the image is scaled to the grid size.
+    pub fn color_at_cell(&self, x: u32, y: u32, grid_size: Size) -> u32 {
+        let image_x = x as usize * self.width as usize / grid_size.w as usize;
+        let image_y = y as usize * self.height as usize / grid_size.h as usize;
+
+        self.pixels[image_x + image_y * self.width as usize]
+    }
+}
+
+fn image_error(message: &str) -> Box<dyn Error> {
+    Box::new(io::Error::other(message))
+}
+
+/// Reads a PNG or PPM image file, detected by its contents.
+pub fn read_image(file_path: &str) -> Result<Image, Box<dyn Error>> {
+    let contents = fs::read(file_path)?;
+
+    if contents.starts_with(b"\x89PNG") {
+        read_png(&contents)
+    } else if contents.starts_with(b"P3") || contents.starts_with(b"P6") {
+        read_ppm(&contents)
+    } else {
+        Err(image_error("Image must be in PNG or PPM format"))
+    }
+}
+
+fn read_png(contents: &[u8]) -> Result<Image, Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(contents);
+    // Expand palettes and low bit depths to 8 bits per sample
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let (info, mut reader) = decoder.read_info()?;
+    let mut buffer = vec![0; info.buffer_size()];
+    reader.next_frame(&mut buffer)?;
+
+    let n_samples = info.color_type.samples();
+    let bytes_per_sample = match info.bit_depth {
+        png::BitDepth::Sixteen => 2,
+        _ => 1,
+    };
+    // Returns the (most significant byte of the) given sample of the pixel at the given index
+    let sample = |pixel_index: usize, sample_index: usize| {
+        buffer[(pixel_index * n_samples + sample_index) * bytes_per_sample] as u32
+    };
+
+    let pixels = (0..info.width as usize * info.height as usize)
+        .map(|pixel_index| match info.color_type {
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha => {
+                let value = sample(pixel_index, 0);
+                (value << 16) | (value << 8) | value
+            }
+            _ => {
+                (sample(pixel_index, 0) << 16)
+                    | (sample(pixel_index, 1) << 8)
+                    | sample(pixel_index, 2)
+            }
+        })
+        .collect();
+
+    Ok(Image {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+/// Reads the whitespace-separated tokens of a PPM image, skipping comments.
+struct PpmTokenizer<'a> {
+    contents: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PpmTokenizer<'a> {
+    fn next_token(&mut self) -> Option<&'a [u8]> {
+        let contents = self.contents;
+        loop {
+            while self.position < contents.len() && contents[self.position].is_ascii_whitespace() {
+                self.position += 1;
+            }
+            if self.position < contents.len() && contents[self.position] == b'#' {
+                while self.position < contents.len() && contents[self.position] != b'\n' {
+                    self.position += 1;
+                }
+            } else {
+                break;
+            }
+        }
+
+        let start = self.position;
+        while self.position < contents.len() && !contents[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+
+        if start < self.position {
+            Some(&contents[start..self.position])
+        } else {
+            None
+        }
+    }
+
+    fn next_number(&mut self) -> Result<u32, Box<dyn Error>> {
+        let token = self
+            .next_token()
+            .ok_or_else(|| image_error("Unexpected end of PPM image"))?;
+
+        Ok(std::str::from_utf8(token)?.parse::<u32>()?)
+    }
+}
+
+/// Reads a PPM image, either in plain (P3) or binary (P6) format.
+fn read_ppm(contents: &[u8]) -> Result<Image, Box<dyn Error>> {
+    let is_binary = contents.starts_with(b"P6");
+
+    // Parse the header: magic number, width, height and maximum value
+    let mut tokenizer = PpmTokenizer {
+        contents,
+        position: 2,
+    };
+    let width = tokenizer.next_number()?;
+    let height = tokenizer.next_number()?;
+    let max_value = tokenizer.next_number()?;
+    if width == 0 || height == 0 || max_value == 0 || max_value > 65535 {
+        return Err(image_error("Invalid PPM image header"));
+    }
+
+    let n_samples = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|n_pixels| n_pixels.checked_mul(3))
+        .ok_or_else(|| image_error("Invalid PPM image header"))?;
+    let samples: Vec<u32> = if is_binary {
+        // A single whitespace character separates the header from the binary data
+        let data_start = tokenizer.position + 1;
+        let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+        let data = n_samples
+            .checked_mul(bytes_per_sample)
+            .and_then(|n_bytes| contents.get(data_start..data_start.checked_add(n_bytes)?))
+            .ok_or_else(|| image_error("Unexpected end of PPM image"))?;
+
+        data.chunks(bytes_per_sample)
+            .map(|bytes| {
+                bytes
+                    .iter()
+                    .fold(0u32, |value, byte| (value << 8) | *byte as u32)
+            })
+            .collect()
+    } else {
+        (0..n_samples)
+            .map(|_| tokenizer.next_number())
+            .collect::<Result<Vec<u32>, Box<dyn Error>>>()?
+    };
+
+    // Scale the samples to 8 bits
+    let pixels = samples
+        .chunks(3)
+        .map(|rgb| {
+            let to_8_bits = |value: u32| value.min(max_value) * 255 / max_value;
+            (to_8_bits(rgb[0]) << 16) | (to_8_bits(rgb[1]) << 8) | to_8_bits(rgb[2])
+        })
+        .collect();
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes an RGB image with the given pixels as a PNG image.
+    fn encode_png(width: u32, height: u32, data: &[u8]) -> Vec<u8> {
+        let mut contents = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut contents, width, height);
+            encoder.set_color(png::ColorType::RGB);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(data).unwrap();
+        }
+        contents
+    }
+
+    /// Computes the CRC-32 checksum of a PNG chunk.
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = 0xffff_ffffu32;
+        for byte in bytes {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    #[test]
+    fn reads_ppm() {
+        let plain = read_ppm(b"P3\n# comment\n2 1\n255\n255 0 0  0 0 255\n").unwrap();
+        assert_eq!((plain.width, plain.height), (2, 1));
+        assert_eq!(plain.pixels, vec![0xff0000, 0x0000ff]);
+
+        let binary = read_ppm(b"P6 1 1 255\n\x00\xff\x00").unwrap();
+        assert_eq!(binary.pixels, vec![0x00ff00]);
+    }
+
+    #[test]
+    fn rejects_truncated_ppm() {
+        assert!(read_ppm(b"P3 2 1 255 255 0 0 0 0").is_err());
+        assert!(read_ppm(b"P6 2 1 255\n\x00\xff\x00\x00").is_err());
+        assert!(read_ppm(b"P6 2 1").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_ppm_header() {
+        assert!(read_ppm(b"P3 0 1 255\n").is_err());
+        assert!(read_ppm(b"P3 1 1 0\n0 0 0").is_err());
+        assert!(read_ppm(b"P3 1 1 70000\n0 0 0").is_err());
+        assert!(read_ppm(b"P3 one 1 255\n0 0 0").is_err());
+    }
+
+    #[test]
+    fn rejects_ppm_with_overflowing_dimensions() {
+        // The number of samples overflows
+        assert!(read_ppm(b"P6 4294967295 4294967295 255\n\x00\x00\x00").is_err());
+        assert!(read_ppm(b"P3 4294967295 4294967295 255\n0 0 0").is_err());
+        // The number of bytes overflows, as the samples take two bytes each
+        assert!(read_ppm(b"P6 4294967295 1431655765 65535\n\x00\x00\x00").is_err());
+    }
+
+    #[test]
+    fn reads_png() {
+        let image = read_png(&encode_png(2, 1, &[255, 0, 0, 0, 0, 255])).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, vec![0xff0000, 0x0000ff]);
+    }
+
+    #[test]
+    fn rejects_truncated_png() {
+        let contents = encode_png(2, 1, &[255, 0, 0, 0, 0, 255]);
+        assert!(read_png(&contents[..contents.len() - 20]).is_err());
+        assert!(read_png(&contents[..20]).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_png_header() {
+        assert!(read_png(b"\x89PNG\r\n\x1a\nnot a png image").is_err());
+        assert!(read_png(b"\x89PNG").is_err());
+    }
+
+    #[test]
+    fn rejects_png_with_overflowing_dimensions() {
+        let mut contents = encode_png(1, 1, &[0, 0, 0]);
+        // Replace the width and height in the header chunk (after the signature, length and type), and its checksum
+        contents[16..20].copy_from_slice(&0x7fff_ffffu32.to_be_bytes());
+        contents[20..24].copy_from_slice(&0x7fff_ffffu32.to_be_bytes());
+        let crc = crc32(&contents[12..29]);
+        contents[29..33].copy_from_slice(&crc.to_be_bytes());
+
+        assert!(read_png(&contents).is_err());
+    }
+}
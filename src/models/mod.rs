@@ -1,12 +1,30 @@
-use crate::util::{time_ns, PRng, Size};
+use crate::util::{time_ns, PRng};
 use rand::{Rng, SeedableRng};
 use std::collections::BTreeMap;
+use std::error::Error;
 
 pub mod params;
-pub use params::{ModelParams, ModelType};
+pub use params::{ModelParams, ModelType, NeighborhoodParams};
 
 pub mod grid;
-pub use grid::{Cell, Grid, Quadrant};
+pub use grid::{AnimalState, Cell, Grid, Neighbor, Quadrant};
+
+pub mod neighborhood;
+pub use neighborhood::Neighborhood;
+
+pub mod image;
+
+mod placement;
+
+pub mod terrain;
+
+pub mod rates;
+pub use rates::Rates;
+
+pub mod events;
+
+mod parallel;
+use parallel::{map_cells, thread_count};
 
 mod simple_model;
 use simple_model::SimpleModel;
@@ -14,41 +32,179 @@ use simple_model::SimpleModel;
 mod pppe_model;
 use pppe_model::PPPEModel;
 
+mod dsam_model;
+use dsam_model::DSAMModel;
+
+mod custom_model;
+use custom_model::CustomModel;
+
+/// A predator-prey model, which holds a grid of cells and updates it one time step at a time.
 pub trait Model {
-    fn populate(&mut self);
+    /// Fills the grid with the initial population of every specie.
+    /// Returns an error if the initial image cannot be read.
+    fn populate(&mut self) -> Result<(), Box<dyn Error>>;
+    /// Advances the model by one time step.
     fn tick(&mut self);
     fn get_grid(&self) -> &Grid;
+    /// Returns the grid for interventions between ticks, e.g. events.
+    fn get_grid_mut(&mut self) -> &mut Grid;
     fn get_params(&self) -> &ModelParams;
+    fn get_rng(&self) -> &PRng;
+    /// Returns the random seed the model was created with, which is generated from the time if the parameters do not specify one.
+    fn get_seed(&self) -> u64;
+    /// Describes the cell at (x, y) and the neighbor counts that the model's rules are based on, one line per item.
+    fn inspect_cell(&self, x: u32, y: u32) -> Vec<String>;
+    /// Replaces the model's state, e.g. when resuming from a checkpoint.
+    fn restore(&mut self, grid: Grid, rng: PRng, ticks_elapsed: usize);
 }
 
-pub fn create_model(params: ModelParams) -> Box<dyn Model> {
-    match &params.model {
-        &ModelType::Simple => Box::new(SimpleModel::new(params)),
-        &ModelType::PPPE => Box::new(PPPEModel::new(params)),
-        _ => {
-            panic!("Model {:?} not implemented", &params.model)
-        }
-    }
+/// Creates an empty model of the type given in the parameters. Call `populate` to fill its grid.
+/// Returns an error if a file that the parameters refer to cannot be read.
+pub fn create_model(params: ModelParams) -> Result<Box<dyn Model>, Box<dyn Error>> {
+    Ok(match params.model {
+        ModelType::Simple => Box::new(SimpleModel::new(params)?),
+        ModelType::PPPE => Box::new(PPPEModel::new(params)?),
+        ModelType::DSAM => Box::new(DSAMModel::new(params)?),
+        ModelType::Custom => Box::new(CustomModel::new(params)?),
+    })
+}
+
+/// Creates a model from parameters in JSON format, and fills its grid with the initial population.
+pub fn create_model_from_str(json: &str) -> Result<Box<dyn Model>, Box<dyn Error>> {
+    let mut model = create_model(params::params_from_str(json)?)?;
+    model.populate()?;
+
+    Ok(model)
 }
 
 pub mod utils {
     use super::*;
 
+    /// Returns a newborn animal of the given specie.
+    pub fn newborn_animal(specie_id: u32, params: &ModelParams) -> Cell {
+        Cell::Animal(
+            specie_id,
+            params.get_specie_by_id(specie_id).newborn_state(),
+        )
+    }
+
+    /// Lets the given animal live for one time step: it ages, uses energy and gains energy if it has eaten.
+    /// Returns None if the animal dies of starvation or old age.
+    pub fn live_one_step(
+        specie_id: u32,
+        state: AnimalState,
+        has_eaten: bool,
+        params: &ModelParams,
+    ) -> Option<AnimalState> {
+        let specie_params = params.get_specie_by_id(specie_id);
+        let mut state = state;
+
+        state.age += 1;
+        if let Some(max_lifespan) = specie_params.max_lifespan {
+            if state.age >= max_lifespan {
+                return None;
+            }
+        }
+
+        if let Some(metabolic_cost) = specie_params.metabolic_cost {
+            state.energy -= metabolic_cost;
+            if has_eaten {
+                state.energy += specie_params.energy_per_prey.unwrap_or(1.0);
+            }
+            if state.energy < specie_params.starvation_threshold.unwrap_or(0.0) {
+                return None;
+            }
+        }
+
+        Some(state)
+    }
+
+    /// Describes a number of neighbors, and the most prevalent specie among them.
+    fn describe_neighbor_count(
+        label: &str,
+        count: u32,
+        dominant_specie_id: u32,
+        params: &ModelParams,
+    ) -> String {
+        if count > 0 {
+            format!(
+                "{} {} (mostly {})",
+                label,
+                count,
+                params.specie_name_from_id(dominant_specie_id)
+            )
+        } else {
+            format!("{} 0", label)
+        }
+    }
+
+    /// Describes the cell at (x, y), and the predators, prey and herbivores among the neighbors in each of the given neighborhoods.
+    /// Every neighborhood is named after the phase of the model's rules that uses it, e.g. "Feeding".
+    pub fn describe_cell(
+        grid: &Grid,
+        params: &ModelParams,
+        x: u32,
+        y: u32,
+        neighborhoods: &[(&str, &Vec<Cell>)],
+    ) -> Vec<String> {
+        // Ties between equally prevalent species are broken by a fixed random stream, so the description does not change the model's state
+        let mut rng = PRng::seed_from_u64(0);
+        let cell = grid.get_cell_at(x, y);
+        let mut lines = vec![format!("Cell ({}, {})", x, y)];
+
+        match cell {
+            &Cell::Animal(specie_id, state) => {
+                lines.push(params.specie_name_from_id(specie_id).to_string());
+                lines.push(format!("Energy {:.2}, age {}", state.energy, state.age));
+            }
+            Cell::Empty => lines.push(String::from("Empty")),
+        }
+        match grid.get_habitat_id_at(x, y) {
+            0 => {}
+            habitat_id => lines.push(format!(
+                "Habitat {}",
+                params.habitat_name_from_id(habitat_id)
+            )),
+        }
+
+        for (phase, neighbors) in neighborhoods.iter() {
+            let (n_predators, dominant_predator_id) =
+                get_neighbor_predators(cell, neighbors, params, &mut rng);
+            let (n_prey, dominant_prey_id) = get_neighbor_prey(cell, neighbors, params, &mut rng);
+            let (n_herbivores, dominant_herbivore_id) =
+                get_neighbor_herbivores(neighbors, params, &mut rng);
+            lines.push(format!("{} neighborhood", phase));
+            lines.push(describe_neighbor_count(
+                "  Predators",
+                n_predators,
+                dominant_predator_id,
+                params,
+            ));
+            lines.push(describe_neighbor_count(
+                "  Prey",
+                n_prey,
+                dominant_prey_id,
+                params,
+            ));
+            lines.push(describe_neighbor_count(
+                "  Herbivores",
+                n_herbivores,
+                dominant_herbivore_id,
+                params,
+            ));
+        }
+
+        lines
+    }
+
     /// Returns the count and specie ID of the most occurring neighboring species.
-    fn most_occurring_neighbor(neighbors: &Vec<&Cell>, rng: &mut PRng) -> (u32, u32) {
-        if neighbors.len() > 0 {
+    fn most_occurring_neighbor(neighbors: &[&Cell], rng: &mut PRng) -> (u32, u32) {
+        if !neighbors.is_empty() {
             let mut count_by_specie = BTreeMap::new();
             for neighbor in neighbors {
                 match neighbor {
-                    Cell::Animal(neighbor_specie_id) => {
-                        if (!count_by_specie.contains_key(neighbor_specie_id)) {
-                            count_by_specie.insert(neighbor_specie_id, 1u32);
-                        } else {
-                            count_by_specie.insert(
-                                neighbor_specie_id,
-                                count_by_specie[neighbor_specie_id] + 1u32,
-                            );
-                        }
+                    Cell::Animal(neighbor_specie_id, _) => {
+                        *count_by_specie.entry(neighbor_specie_id).or_insert(0u32) += 1;
                     }
                     Cell::Empty => {}
                 }
@@ -73,18 +229,18 @@ pub mod utils {
     /// Returns the amount of predators in the neighborhood and the specie id of the most prevalent predator.
     pub fn get_neighbor_predators(
         cell: &Cell,
-        neighbors: &Vec<Cell>,
+        neighbors: &[Cell],
         params: &ModelParams,
         rng: &mut PRng,
     ) -> (u32, u32) {
         let predating_neighbors: Vec<&Cell> = neighbors
             .iter()
             .filter(|neighbor| match neighbor {
-                Cell::Animal(neighbor_specie_id) => match cell {
-                    &Cell::Animal(specie_id) => {
+                Cell::Animal(neighbor_specie_id, _) => match *cell {
+                    Cell::Animal(specie_id, _) => {
                         params.is_specie_predator_for(*neighbor_specie_id, specie_id)
                     }
-                    &Cell::Empty => !params.is_specie_herbivore(*neighbor_specie_id),
+                    Cell::Empty => !params.is_specie_herbivore(*neighbor_specie_id),
                 },
                 Cell::Empty => false,
             })
@@ -99,16 +255,16 @@ pub mod utils {
     /// Returns the amount of edible prey in the neighborhood and the specie id of the most prevalent prey.
     pub fn get_neighbor_prey(
         cell: &Cell,
-        neighbors: &Vec<Cell>,
+        neighbors: &[Cell],
         params: &ModelParams,
         rng: &mut PRng,
     ) -> (u32, u32) {
-        match cell {
-            &Cell::Animal(specie_id) => {
+        match *cell {
+            Cell::Animal(specie_id, _) => {
                 let prey_neighbors: Vec<&Cell> = neighbors
                     .iter()
                     .filter(|neighbor| match neighbor {
-                        Cell::Animal(neighbor_specie_id) => {
+                        Cell::Animal(neighbor_specie_id, _) => {
                             params.is_specie_predator_for(specie_id, *neighbor_specie_id)
                         }
                         Cell::Empty => false,
@@ -120,20 +276,22 @@ pub mod utils {
 
                 (n_prey, dominant_prey_id)
             }
-            &Cell::Empty => (0, 0),
+            Cell::Empty => (0, 0),
         }
     }
 
     /// Returns the amount of same-specie herbivores in the neighborhood and the specie id of the most prevalent herbivore.
     pub fn get_neighbor_herbivores(
-        neighbors: &Vec<Cell>,
+        neighbors: &[Cell],
         params: &ModelParams,
         rng: &mut PRng,
     ) -> (u32, u32) {
         let herbivore_neighbors: Vec<&Cell> = neighbors
             .iter()
             .filter(|neighbor| match neighbor {
-                Cell::Animal(neighbor_specie_id) => params.is_specie_herbivore(*neighbor_specie_id),
+                Cell::Animal(neighbor_specie_id, _) => {
+                    params.is_specie_herbivore(*neighbor_specie_id)
+                }
                 Cell::Empty => false,
             })
             .collect();
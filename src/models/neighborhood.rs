@@ -0,0 +1,283 @@
+use crate::models::grid::{topology_quadrants, Quadrant};
+use crate::models::params::{NeighborhoodParams, NeighborhoodShape, Topology};
+use std::collections::BTreeMap;
+
+/// Set of cells surrounding a cell, given as offsets relative to that cell (excluding the cell itself).
+/// In a hexagonal grid the offsets are axial coordinates (dq, dr), otherwise they are grid coordinates (dx, dy).
+#[derive(Clone)]
+pub struct Neighborhood {
+    offsets: Vec<(i32, i32)>,
+    // Offsets by quadrant. Note that the bordering cells between quadrants may be included in multiple quadrants.
+    quadrant_offsets: BTreeMap<Quadrant, Vec<(i32, i32)>>,
+    // Largest distance along a grid axis from a cell to one of its neighbors
+    reach: u32,
+}
+
+/// Returns the angle (in degrees, counterclockwise from east) of the center of the given hexagonal quadrant.
+fn hex_quadrant_angle(quadrant: Quadrant) -> f32 {
+    match quadrant {
+        Quadrant::East => 0.0,
+        Quadrant::NorthEast => 60.0,
+        Quadrant::NorthWest => 120.0,
+        Quadrant::West => 180.0,
+        Quadrant::SouthWest => 240.0,
+        Quadrant::SouthEast => 300.0,
+        Quadrant::North | Quadrant::South => f32::NAN, // Not used in a hexagonal grid
+    }
+}
+
+/// Returns whether the given offset lies inside the given quadrant.
+fn is_inside_quadrant(topology: Topology, (i, j): (i32, i32), quadrant: Quadrant) -> bool {
+    match topology {
+        Topology::Square => match quadrant {
+            Quadrant::East => i > 0 && j >= -i && j <= i,
+            Quadrant::North => j < 0 && i >= j && i <= -j,
+            Quadrant::West => i < 0 && j >= i && j <= -i,
+            Quadrant::South => j > 0 && i >= -j && i <= j,
+            _ => false, // Not used in a square grid
+        },
+        Topology::Hexagonal => {
+            // Compare the angle towards the neighbor's center with the angle of the quadrant
+            let center_x = 3f32.sqrt() * (i as f32 + j as f32 / 2.0);
+            let center_y = -1.5 * j as f32;
+            let angle = center_y.atan2(center_x).to_degrees();
+            let difference = (angle - hex_quadrant_angle(quadrant)).rem_euclid(360.0);
+            difference.min(360.0 - difference) <= 30.0 + 1e-3
+        }
+    }
+}
+
+impl Neighborhood {
+    pub fn new(topology: Topology, params: &NeighborhoodParams) -> Neighborhood {
+        let mut offsets = vec![];
+
+        match &params.shape {
+            NeighborhoodShape::Kernel(rows) => {
+                let radius = (rows.len() / 2) as i32;
+                for (j, row) in rows.iter().enumerate() {
+                    for (i, c) in row.chars().enumerate() {
+                        let offset = (i as i32 - radius, j as i32 - radius);
+                        if c == '1' && offset != (0, 0) {
+                            offsets.push(offset);
+                        }
+                    }
+                }
+            }
+            shape => {
+                let radius = params.radius as i32;
+                // In a hexagonal grid, cells within the Euclidean distance may lie further than the radius in axial coordinates
+                let search_radius = match (topology, shape) {
+                    (Topology::Hexagonal, NeighborhoodShape::Circular) => {
+                        (radius as f32 * 2.0 / 3f32.sqrt()).ceil() as i32
+                    }
+                    _ => radius,
+                };
+
+                for i in -search_radius..(search_radius + 1) {
+                    for j in -search_radius..(search_radius + 1) {
+                        if i == 0 && j == 0 {
+                            continue;
+                        }
+
+                        let is_inside = match topology {
+                            Topology::Square => match shape {
+                                NeighborhoodShape::Moore => true,
+                                NeighborhoodShape::VonNeumann => i.abs() + j.abs() <= radius,
+                                _ => i * i + j * j <= radius * radius,
+                            },
+                            Topology::Hexagonal => match shape {
+                                NeighborhoodShape::Moore | NeighborhoodShape::VonNeumann => {
+                                    (i + j).abs() <= radius
+                                }
+                                _ => {
+                                    let center_x = i as f32 + j as f32 / 2.0;
+                                    let center_y = j as f32 * 3f32.sqrt() / 2.0;
+                                    center_x * center_x + center_y * center_y
+                                        <= (radius * radius) as f32 + 1e-3
+                                }
+                            },
+                        };
+
+                        if is_inside {
+                            offsets.push((i, j));
+                        }
+                    }
+                }
+            }
+        }
+
+        let quadrant_offsets = topology_quadrants(topology)
+            .iter()
+            .map(|quadrant| {
+                (
+                    *quadrant,
+                    offsets
+                        .iter()
+                        .filter(|offset| is_inside_quadrant(topology, **offset, *quadrant))
+                        .copied()
+                        .collect(),
+                )
+            })
+            .collect();
+
+        // An axial offset (dq, dr) moves at most |dq| + |dr| cells along a grid axis
+        let reach = offsets
+            .iter()
+            .map(|&(i, j)| match topology {
+                Topology::Square => i.abs().max(j.abs()),
+                Topology::Hexagonal => i.abs() + j.abs(),
+            })
+            .max()
+            .unwrap_or(0) as u32;
+
+        Neighborhood {
+            offsets,
+            quadrant_offsets,
+            reach,
+        }
+    }
+
+    /// Returns the largest distance along a grid axis from a cell to one of its neighbors.
+    /// All neighbors of a cell at least this far from every edge of the grid lie inside the grid.
+    pub fn get_reach(&self) -> u32 {
+        self.reach
+    }
+
+    /// Returns the offsets of the neighborhood, optionally filtered down to a specific quadrant.
+    pub fn get_offsets(&self, quadrant: Option<Quadrant>) -> &[(i32, i32)] {
+        match quadrant {
+            None => &self.offsets,
+            Some(quadrant) => self
+                .quadrant_offsets
+                .get(&quadrant)
+                .map(|offsets| offsets.as_slice())
+                .unwrap_or(&[]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn neighborhood(topology: Topology, shape: NeighborhoodShape, radius: u32) -> Neighborhood {
+        Neighborhood::new(topology, &NeighborhoodParams { shape, radius })
+    }
+
+    fn kernel(rows: &[&str]) -> Neighborhood {
+        let rows = rows.iter().map(|row| row.to_string()).collect();
+        neighborhood(Topology::Square, NeighborhoodShape::Kernel(rows), 1)
+    }
+
+    fn sorted(offsets: &[(i32, i32)]) -> Vec<(i32, i32)> {
+        let mut offsets = offsets.to_vec();
+        offsets.sort_unstable();
+        offsets
+    }
+
+    #[test]
+    fn square_shapes_have_expected_sizes() {
+        let expected_sizes = [
+            (NeighborhoodShape::Moore, 8, 24),
+            (NeighborhoodShape::VonNeumann, 4, 12),
+            (NeighborhoodShape::Circular, 4, 12),
+        ];
+        for (shape, size_radius_1, size_radius_2) in expected_sizes.iter() {
+            let radius_1 = neighborhood(Topology::Square, shape.clone(), 1);
+            let radius_2 = neighborhood(Topology::Square, shape.clone(), 2);
+            assert_eq!(radius_1.get_offsets(None).len(), *size_radius_1);
+            assert_eq!(radius_2.get_offsets(None).len(), *size_radius_2);
+            assert_eq!(radius_1.get_reach(), 1);
+            assert_eq!(radius_2.get_reach(), 2);
+        }
+    }
+
+    #[test]
+    fn square_von_neumann_and_circular_differ_at_radius_2() {
+        let von_neumann = neighborhood(Topology::Square, NeighborhoodShape::VonNeumann, 2);
+        let circular = neighborhood(Topology::Square, NeighborhoodShape::Circular, 2);
+
+        // (1, 1) lies within both, (2, 1) within neither, (2, 0) within both
+        for offsets in [von_neumann.get_offsets(None), circular.get_offsets(None)].iter() {
+            assert!(offsets.contains(&(1, 1)));
+            assert!(offsets.contains(&(2, 0)));
+            assert!(!offsets.contains(&(2, 1)));
+            assert!(!offsets.contains(&(0, 0)));
+        }
+    }
+
+    #[test]
+    fn hex_shapes_have_expected_sizes() {
+        let shapes = [
+            NeighborhoodShape::Moore,
+            NeighborhoodShape::VonNeumann,
+            NeighborhoodShape::Circular,
+        ];
+        for shape in shapes.iter() {
+            let radius_1 = neighborhood(Topology::Hexagonal, shape.clone(), 1);
+            let radius_2 = neighborhood(Topology::Hexagonal, shape.clone(), 2);
+            assert_eq!(radius_1.get_offsets(None).len(), 6);
+            assert_eq!(radius_2.get_offsets(None).len(), 18);
+        }
+
+        let radius_1 = neighborhood(Topology::Hexagonal, NeighborhoodShape::VonNeumann, 1);
+        assert_eq!(
+            sorted(radius_1.get_offsets(None)),
+            vec![(-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0)]
+        );
+    }
+
+    #[test]
+    fn kernel_includes_only_marked_cells() {
+        let plus = kernel(&["010", "111", "010"]);
+        assert_eq!(
+            sorted(plus.get_offsets(None)),
+            vec![(-1, 0), (0, -1), (0, 1), (1, 0)]
+        );
+        assert_eq!(plus.get_reach(), 1);
+
+        let corners = kernel(&["10001", "00000", "00000", "00000", "10001"]);
+        assert_eq!(
+            sorted(corners.get_offsets(None)),
+            vec![(-2, -2), (-2, 2), (2, -2), (2, 2)]
+        );
+        assert_eq!(corners.get_reach(), 2);
+    }
+
+    #[test]
+    fn kernel_with_only_the_center_is_empty() {
+        assert!(kernel(&["000", "010", "000"]).get_offsets(None).is_empty());
+    }
+
+    #[test]
+    fn square_quadrants_select_offsets_in_their_direction() {
+        let moore = neighborhood(Topology::Square, NeighborhoodShape::Moore, 1);
+
+        assert_eq!(
+            sorted(moore.get_offsets(Some(Quadrant::East))),
+            vec![(1, -1), (1, 0), (1, 1)]
+        );
+        assert_eq!(
+            sorted(moore.get_offsets(Some(Quadrant::North))),
+            vec![(-1, -1), (0, -1), (1, -1)]
+        );
+        // Quadrants of the other topology contain no offsets
+        assert!(moore.get_offsets(Some(Quadrant::NorthEast)).is_empty());
+    }
+
+    #[test]
+    fn hex_quadrants_select_offsets_in_their_direction() {
+        let von_neumann = neighborhood(Topology::Hexagonal, NeighborhoodShape::VonNeumann, 1);
+
+        assert_eq!(von_neumann.get_offsets(Some(Quadrant::East)), &[(1, 0)]);
+        assert_eq!(
+            von_neumann.get_offsets(Some(Quadrant::NorthEast)),
+            &[(1, -1)]
+        );
+        assert_eq!(
+            von_neumann.get_offsets(Some(Quadrant::SouthWest)),
+            &[(-1, 1)]
+        );
+        assert!(von_neumann.get_offsets(Some(Quadrant::North)).is_empty());
+    }
+}
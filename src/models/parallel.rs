@@ -0,0 +1,126 @@
+use crate::util::{PRng, Size};
+use rand::SeedableRng;
+use std::thread;
+
+/// Derives the seed of an independent random stream from a seed and a stream index (SplitMix64).
+pub fn stream_seed(seed: u64, stream: u64) -> u64 {
+    let mut z = seed.wrapping_add(stream.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Returns the number of threads to use, defaulting to the number of CPU cores.
+pub fn thread_count(threads: Option<usize>) -> usize {
+    threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+        .max(1)
+}
+
+/// Computes a value for every cell of a grid of the given size, in row-major order.
+///
+/// The rows are divided into stripes that are processed on separate threads.
+/// Every row has its own random stream derived from the given seed, so the results do not depend on the number of threads.
+pub fn map_cells<T, F>(size: Size, seed: u64, n_threads: usize, cell_fn: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(u32, u32, &mut PRng) -> T + Sync,
+{
+    let map_rows = |rows: std::ops::Range<u32>| {
+        let mut values = Vec::with_capacity(rows.len() * size.w as usize);
+        for y in rows {
+            let mut rng = PRng::seed_from_u64(stream_seed(seed, y as u64));
+            for x in 0..size.w {
+                values.push(cell_fn(x, y, &mut rng));
+            }
+        }
+        values
+    };
+
+    let n_threads = n_threads.min(size.h as usize).max(1) as u32;
+    if n_threads == 1 {
+        return map_rows(0..size.h);
+    }
+
+    let map_rows = &map_rows;
+    let rows_per_thread = size.h.div_ceil(n_threads);
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..size.h)
+            .step_by(rows_per_thread as usize)
+            .map(|y_start| {
+                let rows = y_start..(y_start + rows_per_thread).min(size.h);
+                scope.spawn(move || map_rows(rows))
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("Worker thread panicked"))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::params::params_from_file_with_seed;
+    use crate::models::{create_model, Model};
+    use rand::Rng;
+
+    const CONFIGS: [&str; 6] = [
+        "custom-fish.json",
+        "dsam-fish.json",
+        "pppe-coast.json",
+        "pppe-fish.json",
+        "pppe-hex.json",
+        "simple-fish.json",
+    ];
+    const SEED: u64 = 42;
+    // Smaller than the sample configs' grids, to keep the tests fast in debug builds.
+    const GRID_SIZE: Size = Size::new(24, 24);
+    const TICKS: usize = 30;
+
+    /// Creates and populates a model from a sample config, using the given number of threads.
+    fn create_sample_model(config_name: &str, threads: usize) -> Box<dyn Model> {
+        let config_path = format!("sample-configs/{}", config_name);
+        let mut params = params_from_file_with_seed(&config_path, Some(SEED)).unwrap();
+        params.grid_size = GRID_SIZE;
+        params.threads = Some(threads);
+
+        let mut model = create_model(params).unwrap();
+        model.populate().unwrap();
+        model
+    }
+
+    #[test]
+    fn map_cells_does_not_depend_on_thread_count() {
+        let size = Size::new(7, 13);
+        let single_threaded = map_cells(size, SEED, 1, |x, y, rng| (x, y, rng.gen::<u64>()));
+
+        for n_threads in 2..=16 {
+            let multi_threaded =
+                map_cells(size, SEED, n_threads, |x, y, rng| (x, y, rng.gen::<u64>()));
+            assert_eq!(single_threaded, multi_threaded, "{} threads", n_threads);
+        }
+    }
+
+    #[test]
+    fn results_do_not_depend_on_thread_count() {
+        for config_name in CONFIGS.iter() {
+            let mut single_threaded = create_sample_model(config_name, 1);
+            let mut multi_threaded = create_sample_model(config_name, 4);
+
+            for _ in 0..TICKS {
+                single_threaded.tick();
+                multi_threaded.tick();
+            }
+
+            assert!(
+                serde_json::to_string(single_threaded.get_grid()).unwrap()
+                    == serde_json::to_string(multi_threaded.get_grid()).unwrap(),
+                "grids differ between 1 and 4 threads for {}",
+                config_name
+            );
+        }
+    }
+}
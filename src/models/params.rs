@@ -1,3 +1,6 @@
+use crate::models::grid::{AnimalState, Boundaries, Boundary};
+use crate::models::image::{read_image, Image};
+use crate::models::neighborhood::Neighborhood;
 use crate::util::Size;
 use bimap::BiMap;
 use serde::{Deserialize, Serialize};
@@ -5,8 +8,11 @@ use std::collections::BTreeMap;
 use std::error::Error;
 use std::fs;
 use std::io;
+use std::ops::Range;
+use std::path::Path;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum ModelType {
     Simple,
     PPPE,
@@ -14,13 +20,380 @@ pub enum ModelType {
     Custom,
 }
 
+/// Shape of the grid's cells.
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub enum Topology {
+    Square,    // Square cells with four adjacent cells
+    Hexagonal, // Hexagonal cells with six adjacent cells, odd rows are shifted half a cell to the right
+}
+
+/// Shape of a cell's neighborhood.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum NeighborhoodShape {
+    Moore, // Cells in the square with the given radius (in a hexagonal grid: the hexagon with the given radius)
+    VonNeumann, // Cells within the given Manhattan distance (in a hexagonal grid: the hexagon with the given radius)
+    Circular,   // Cells within the given Euclidean distance
+    // Cells marked with 1 in a square mask of rows, e.g. ["010", "101", "010"], centered on the cell.
+    // The radius is derived from the mask. In a hexagonal grid, the rows and columns are axial coordinates.
+    Kernel(Vec<String>),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NeighborhoodParams {
+    pub shape: NeighborhoodShape,
+    #[serde(default = "default_neighborhood_radius")]
+    pub radius: u32,
+}
+
+impl NeighborhoodParams {
+    pub fn moore(radius: u32) -> NeighborhoodParams {
+        NeighborhoodParams {
+            shape: NeighborhoodShape::Moore,
+            radius,
+        }
+    }
+
+    pub fn von_neumann(radius: u32) -> NeighborhoodParams {
+        NeighborhoodParams {
+            shape: NeighborhoodShape::VonNeumann,
+            radius,
+        }
+    }
+}
+
+/// Neighborhoods used in each phase of the models. Phases without a neighborhood use the model's default.
+#[derive(Clone, Serialize, Deserialize, Default)]
+pub struct NeighborhoodsParams {
+    pub interaction: Option<NeighborhoodParams>, // Simple model (default: Moore, radius 1), custom model (default: Moore, radius 1) and DSAM model (default: Von Neumann, radius 1)
+    pub feeding: Option<NeighborhoodParams>, // PPPE feeding phase (default: Von Neumann, radius 1)
+    pub reproduction: Option<NeighborhoodParams>, // PPPE reproduction phase (default: Von Neumann, radius 1)
+    pub movement: Option<NeighborhoodParams>, // PPPE movement phase (default: Moore, radius sense_radius)
+}
+
+/// Determines how the neighborhood of cells at an edge of the grid is calculated.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum BoundaryMode {
+    Bounded,       // Cells outside the grid are missing, edge cells have fewer neighbors
+    Periodic,      // The grid wraps around to the opposite edge, which must also be periodic
+    Reflecting,    // Cells outside the grid mirror the cells inside the grid
+    Absorbing,     // Cells outside the grid count as empty cells
+    Fixed(String), // Cells outside the grid count as animals of the given specie
+}
+
+/// Boundary modes of the grid, either one mode for all edges or a mode per edge.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BoundaryParams {
+    Uniform(BoundaryMode),
+    PerEdge {
+        #[serde(default = "default_boundary_mode")]
+        west: BoundaryMode,
+        #[serde(default = "default_boundary_mode")]
+        north: BoundaryMode,
+        #[serde(default = "default_boundary_mode")]
+        east: BoundaryMode,
+        #[serde(default = "default_boundary_mode")]
+        south: BoundaryMode,
+    },
+}
+
+impl BoundaryParams {
+    /// Returns the boundary modes of the west, north, east and south edges.
+    pub fn edges(&self) -> [&BoundaryMode; 4] {
+        match self {
+            BoundaryParams::Uniform(mode) => [mode, mode, mode, mode],
+            BoundaryParams::PerEdge {
+                west,
+                north,
+                east,
+                south,
+            } => [west, north, east, south],
+        }
+    }
+}
+
+/// A type of terrain, which restricts the species that may occupy its cells.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HabitatParams {
+    pub color: Option<String>, // (optional) Color of empty cells of this habitat, in hex format (e.g. 0000FF)
+    #[serde(default)]
+    pub obstacle: bool, // If true, nothing can enter, breed into or sense through cells of this habitat
+    pub species: Option<Vec<String>>, // (optional) Species that may occupy cells of this habitat (default: all species)
+}
+
+/// Layout of the habitats on the grid, as a map of characters. The map is scaled to the grid size.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TerrainParams {
+    pub map: Option<Vec<String>>, // Rows of the map, e.g. ["..##", "~~.."]
+    pub map_file: Option<String>, // Text file containing the rows of the map (instead of map), relative to the config file
+    pub legend: BTreeMap<char, String>, // Mapping from map character -> habitat name, other characters are open terrain
+}
+
+/// A rectangular region of the grid, as fractions of the grid size (e.g. x: 0.0, y: 0.0, w: 0.5, h: 1.0 is the west half).
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct RegionParams {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl RegionParams {
+    /// Returns the ranges of x and y coordinates of the cells in the region.
+    pub fn cell_ranges(&self, size: Size) -> (Range<u32>, Range<u32>) {
+        let x_start = (self.x * size.w as f32).round() as u32;
+        let y_start = (self.y * size.h as f32).round() as u32;
+        let x_end = ((self.x + self.w) * size.w as f32).round() as u32;
+        let y_end = ((self.y + self.h) * size.h as f32).round() as u32;
+
+        (
+            x_start.min(size.w)..x_end.min(size.w),
+            y_start.min(size.h)..y_end.min(size.h),
+        )
+    }
+
+    /// Returns whether the region lies within the grid.
+    pub fn is_valid(&self) -> bool {
+        [
+            self.x,
+            self.y,
+            self.w,
+            self.h,
+            self.x + self.w,
+            self.y + self.h,
+        ]
+        .iter()
+        .all(|value| *value >= 0.0 && *value <= 1.0)
+    }
+}
+
+/// A species rate that varies over the grid.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum RateFieldParams {
+    // Linear gradient from the west to the east edge (or from the north to the south edge if vertical)
+    Gradient {
+        from: f32,
+        to: f32,
+        #[serde(default)]
+        vertical: bool,
+    },
+    // Grayscale PNG or PPM image, relative to the config file and scaled to the grid size. Black is min and white is max.
+    // The file is read into the image when the parameters are loaded, so that checkpoints do not depend on the file.
+    Image {
+        file: Option<String>,
+        #[serde(default)]
+        image: Option<Image>,
+        min: f32,
+        max: f32,
+    },
+    // Value per named region, and the default value outside of them. Where regions overlap, the last region (alphabetically) applies.
+    Regions {
+        default: f32,
+        values: BTreeMap<String, f32>,
+    },
+}
+
+/// Value of a species rate, either a single number or a field that varies over the grid.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RateParams {
+    Constant(f32),
+    Field(RateFieldParams),
+}
+
+/// A range of ticks in a piecewise-constant schedule.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScheduleRange {
+    pub from: usize, // First tick of the range
+    pub to: usize,   // Tick after the last tick of the range
+    pub factor: f32,
+}
+
+/// A point of a time series, which applies from the given tick until the next point.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SchedulePoint {
+    pub tick: usize,
+    pub factor: f32,
+}
+
+/// Schedule that scales a species rate over time. In every tick, the rate is multiplied by the schedule's factor.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum ScheduleParams {
+    // Factor per range of ticks, 1.0 outside of the ranges. If a period is given, the ranges repeat every period ticks.
+    Piecewise {
+        ranges: Vec<ScheduleRange>,
+        period: Option<usize>,
+    },
+    // Factor 1.0 + amplitude * sin(2 * pi * (tick + phase) / period)
+    Sinusoidal {
+        period: f32,
+        amplitude: f32,
+        #[serde(default)]
+        phase: f32,
+    },
+    // Factors read from a CSV file (relative to the config file) with a tick and a factor column, or given as points.
+    // The last point at or before the current tick applies. If a period is given, the series repeats every period ticks.
+    TimeSeries {
+        file: Option<String>,
+        #[serde(default)]
+        points: Vec<SchedulePoint>,
+        period: Option<usize>,
+    },
+}
+
+/// An intervention that acts on the grid at a given tick.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EventParams {
+    pub tick: usize, // The event takes place right after this tick
+    pub action: EventAction,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum EventAction {
+    // Removes the given fraction of the animals of a specie (e.g. a cull or fishing event), optionally only within a region
+    Cull {
+        specie: String,
+        fraction: f32,
+        region: Option<String>,
+    },
+    // Introduces a number of newborn animals of a specie at random empty cells, optionally only within a region
+    Introduce {
+        specie: String,
+        count: usize,
+        region: Option<String>,
+    },
+    // Removes all animals within a region (e.g. a disaster)
+    Clear {
+        region: String,
+    },
+}
+
+/// Strategy for placing the initial population of a specie.
+/// The density is the probability that a cell in the shape is occupied (default: 1.0).
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Placement {
+    // Clusters around random centers, whose density falls off as a Gaussian with the given radius (standard deviation, in cells)
+    Blobs {
+        count: u32,
+        radius: f32,
+        #[serde(default = "default_placement_density")]
+        density: f32,
+    },
+    // Rectangular region, as fractions of the grid size (see RegionParams)
+    Rectangle {
+        x: f32,
+        y: f32,
+        w: f32,
+        h: f32,
+        #[serde(default = "default_placement_density")]
+        density: f32,
+    },
+    // Horizontal (or vertical) stripes of the given width, separated by the given spacing (in cells)
+    Stripes {
+        width: u32,
+        spacing: u32,
+        #[serde(default)]
+        vertical: bool,
+        #[serde(default = "default_placement_density")]
+        density: f32,
+    },
+    // Squares of the given size (in cells) in a checkerboard pattern, either on the even or the odd squares
+    Checkerboard {
+        size: u32,
+        #[serde(default)]
+        odd: bool,
+        #[serde(default = "default_placement_density")]
+        density: f32,
+    },
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SpecieParams {
     pub color: Option<String>, // (optional) Cell color of the specie, in hex format (e.g. FF0000)
+    #[serde(default)]
     pub initial_population: f32, // Initial population on the grid divided by the total amount of cells
-    pub birth_rate: f32, // Probability of birth in a given time step (depending on neighboring cells)
-    pub death_rate: f32, // Probability of death in a given time step (depending on neighboring cells)
+    pub placement: Option<Vec<Placement>>, // (optional) Placement of the initial population, instead of at random positions
+    pub birth_rate: RateParams, // Probability of birth in a given time step (depending on neighboring cells)
+    pub death_rate: RateParams, // Probability of death in a given time step (depending on neighboring cells)
+    pub birth_rate_schedule: Option<ScheduleParams>, // (optional) Schedule that scales the birth rate over time
+    pub death_rate_schedule: Option<ScheduleParams>, // (optional) Schedule that scales the death rate over time
     pub energy_sources: Option<Vec<String>>, // (optional) Other species that may be used as an energy source (predator-prey relationship)
+    pub metabolic_cost: Option<f32>, // (optional) Energy used per time step. If set, predators die of starvation instead of by their death rate
+    pub energy_per_prey: Option<f32>, // (optional) Energy gained by eating prey (default: 1.0)
+    pub initial_energy: Option<f32>, // (optional) Energy of a newborn animal (default: 1.0)
+    pub starvation_threshold: Option<f32>, // (optional) Energy below which the animal dies of starvation (default: 0.0)
+    pub max_lifespan: Option<u32>, // (optional) Number of time steps after which the animal dies of old age
+}
+
+impl SpecieParams {
+    /// Returns whether the specie tracks energy, i.e. dies of starvation instead of by its death rate.
+    pub fn uses_energy(&self) -> bool {
+        self.metabolic_cost.is_some()
+    }
+
+    pub fn newborn_state(&self) -> AnimalState {
+        AnimalState {
+            energy: self.initial_energy.unwrap_or(1.0),
+            age: 0,
+        }
+    }
+}
+
+/// Neighbor quantity that a transition rule can depend on.
+#[derive(Copy, Clone, Serialize, Deserialize, Debug)]
+pub enum NeighborQuantity {
+    Predators,  // Neighbors that may eat the cell (any non-herbivore if the cell is empty)
+    Prey,       // Neighbors that the cell may eat
+    Herbivores, // Neighbors of the most common herbivore specie
+    Empty,      // Empty neighbors
+}
+
+/// Matches the current state of a cell in a transition rule.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum CellMatcher {
+    Any,
+    Empty,
+    Herbivore,
+    Predator,
+    Specie(String),
+}
+
+/// Next state of a cell in a transition rule.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum CellOutcome {
+    Empty,
+    Specie(String),
+    DominantPredator,  // Most prevalent neighboring predator
+    DominantPrey,      // Most prevalent neighboring prey
+    DominantHerbivore, // Most prevalent neighboring herbivore
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RuleCondition {
+    pub neighbors: NeighborQuantity,
+    pub min: Option<u32>, // (optional) Minimum amount of neighbors (inclusive)
+    pub max: Option<u32>, // (optional) Maximum amount of neighbors (inclusive)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RuleOutcome {
+    pub to: CellOutcome,
+    pub probability: f32, // Probability of this outcome in a given time step
+    // (optional) If set, the probability applies per neighbor of the given kind, i.e. 1 - (1 - p)^n
+    pub per_neighbor: Option<NeighborQuantity>,
+}
+
+/// Transition rule of the custom model.
+/// A rule applies to a cell if the cell matches `from` and all conditions hold.
+/// It then picks at most one outcome: each outcome's probability is the chance that it is picked, and the remainder
+/// (1.0 minus the sum of the probabilities) is the chance that the cell is left unchanged. If per-neighbor probabilities
+/// add up to more than 1.0, they are scaled down to add up to 1.0, so that the cell always changes.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TransitionRule {
+    pub from: CellMatcher,
+    #[serde(default)]
+    pub conditions: Vec<RuleCondition>,
+    pub outcomes: Vec<RuleOutcome>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -30,13 +403,55 @@ pub struct ModelParams {
     pub species: BTreeMap<String, SpecieParams>,
     #[serde(default = "default_specie_sense_radius")]
     pub sense_radius: u32,
+    #[serde(default)]
+    pub neighborhoods: NeighborhoodsParams,
 
     pub grid_size: Size,
+    #[serde(default = "default_topology")]
+    pub topology: Topology,
+    #[serde(default = "default_boundary")]
+    pub boundary: BoundaryParams,
     pub random_seed: Option<u64>,
+    pub threads: Option<usize>, // (optional) Number of threads used per tick, defaults to the number of CPU cores
+    // Mapping from habitat name -> habitat params
+    #[serde(default)]
+    pub habitats: BTreeMap<String, HabitatParams>,
+    pub terrain: Option<TerrainParams>, // (optional) Layout of the habitats, the whole grid is open terrain if absent
+    // Mapping from region name -> region, used by spatially varying rates and events
+    #[serde(default)]
+    pub regions: BTreeMap<String, RegionParams>,
+    // Interventions that act on the grid during the run
+    #[serde(default)]
+    pub events: Vec<EventParams>,
+    // (optional) PNG or PPM image with the initial layout of the grid, relative to the config file. Scaled to the grid size.
+    // Pixels with the color of a specie or habitat become that specie or habitat, other pixels become empty cells.
+    // Replaces the random initial population.
+    pub initial_image: Option<String>,
+    // The initial image, read from its file when loading the parameters
+    #[serde(default)]
+    pub initial_image_data: Option<Image>,
+    // Transition rules, evaluated in order (only used by the custom model)
+    #[serde(default)]
+    pub rules: Vec<TransitionRule>,
 }
 fn default_specie_sense_radius() -> u32 {
     2
 }
+fn default_neighborhood_radius() -> u32 {
+    1
+}
+fn default_placement_density() -> f32 {
+    1.0
+}
+fn default_topology() -> Topology {
+    Topology::Square
+}
+fn default_boundary_mode() -> BoundaryMode {
+    BoundaryMode::Bounded
+}
+fn default_boundary() -> BoundaryParams {
+    BoundaryParams::Uniform(default_boundary_mode())
+}
 
 impl ModelParams {
     /// Returns the mapping from specie name -> specie id
@@ -73,6 +488,88 @@ impl ModelParams {
         &self.species[self.specie_name_from_id(specie_id)]
     }
 
+    /// Returns the id of the given habitat. Habitat ids start at 1, 0 is open terrain.
+    pub fn habitat_id_from_name(&self, habitat_name: &str) -> u32 {
+        for (index, name) in self.habitats.keys().enumerate() {
+            if name == habitat_name {
+                return index as u32 + 1;
+            }
+        }
+
+        panic!("Could not find habitat {}", habitat_name)
+    }
+
+    pub fn habitat_name_from_id(&self, habitat_id: u32) -> &str {
+        self.habitats
+            .keys()
+            .nth(habitat_id as usize - 1)
+            .expect("Could not find habitat")
+    }
+
+    pub fn get_habitat_by_id(&self, habitat_id: u32) -> &HabitatParams {
+        self.habitats
+            .values()
+            .nth(habitat_id as usize - 1)
+            .expect("Could not find habitat")
+    }
+
+    /// Returns the mapping from color (in 0xRRGGBB format) -> specie id, for species that have a color.
+    pub fn specie_colors(&self) -> BTreeMap<u32, u32> {
+        self.species
+            .keys()
+            .enumerate()
+            .filter_map(|(index, specie_name)| {
+                let color = parse_hex_color(self.species[specie_name].color.as_ref()?)?;
+                Some((color, index as u32 + 1))
+            })
+            .collect()
+    }
+
+    /// Returns the mapping from color (in 0xRRGGBB format) -> habitat id, for habitats that have a color.
+    pub fn habitat_colors(&self) -> BTreeMap<u32, u32> {
+        self.habitats
+            .values()
+            .enumerate()
+            .filter_map(|(index, habitat_params)| {
+                let color = parse_hex_color(habitat_params.color.as_ref()?)?;
+                Some((color, index as u32 + 1))
+            })
+            .collect()
+    }
+
+    /// Returns the initial image, if any. The image has been read from its file when loading the parameters,
+    /// unless they were created otherwise.
+    pub fn get_initial_image(&self) -> Result<Option<Image>, Box<dyn Error>> {
+        match (&self.initial_image_data, &self.initial_image) {
+            (Some(image), _) => Ok(Some(image.clone())),
+            (None, Some(file)) => Ok(Some(read_image(file).map_err(|error| {
+                io::Error::other(format!("Failed to read initial image {}: {}", file, error))
+            })?)),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Returns the boundaries of the grid, with specie names resolved to specie ids.
+    pub fn grid_boundaries(&self) -> Boundaries {
+        let [west, north, east, south] = self.boundary.edges();
+        let to_boundary = |mode: &BoundaryMode| match mode {
+            BoundaryMode::Bounded => Boundary::Bounded,
+            BoundaryMode::Periodic => Boundary::Periodic,
+            BoundaryMode::Reflecting => Boundary::Reflecting,
+            BoundaryMode::Absorbing => Boundary::Absorbing,
+            BoundaryMode::Fixed(specie_name) => {
+                Boundary::Fixed(self.specie_id_from_name(specie_name))
+            }
+        };
+
+        Boundaries {
+            west: to_boundary(west),
+            north: to_boundary(north),
+            east: to_boundary(east),
+            south: to_boundary(south),
+        }
+    }
+
     /// Returns whether the given specie is a herbivore, i.e. does not eat any other species.
     pub fn is_specie_herbivore(&self, specie_id: u32) -> bool {
         self.species[self.specie_name_from_id(specie_id)]
@@ -93,12 +590,156 @@ impl ModelParams {
     }
 }
 
+// Parses a color in hex format (e.g. FF0000) to 0xRRGGBB format.
+pub fn parse_hex_color(hex_color: &str) -> Option<u32> {
+    if hex_color.len() == 6 {
+        u32::from_str_radix(hex_color, 16).ok()
+    } else {
+        None
+    }
+}
+
 // Loads model parameters from a file. Returns an error if the file could not be read or if the contents are invalid.
 pub fn params_from_file(file_path: &str) -> Result<ModelParams, Box<dyn Error>> {
     let file_contents = fs::read_to_string(file_path)?;
+    let mut params: ModelParams = serde_json::from_str(&file_contents)?;
+
+    load_files(&mut params, Path::new(file_path).parent())?;
+    validate_params(&params)?;
+
+    Ok(params)
+}
+
+/// Loads the parameters from file, overriding the random seed if one is given.
+pub fn params_from_file_with_seed(
+    file_path: &str,
+    random_seed: Option<u64>,
+) -> Result<ModelParams, Box<dyn Error>> {
+    let mut params = params_from_file(file_path)?;
+    if random_seed.is_some() {
+        params.random_seed = random_seed;
+    }
+
+    Ok(params)
+}
+
+// Loads model parameters from a JSON string. Returns an error if the contents are invalid.
+// Files referred to in the parameters are relative to the working directory.
+pub fn params_from_str(json: &str) -> Result<ModelParams, Box<dyn Error>> {
+    let mut params: ModelParams = serde_json::from_str(json)?;
+
+    load_files(&mut params, None)?;
+    validate_params(&params)?;
 
-    let params: ModelParams = serde_json::from_str(&file_contents)?;
+    Ok(params)
+}
+
+// Reads the terrain map file into the map, the initial image and rate images into their images and time series files
+// into their points, so that the parameters no longer depend on the files. The files are relative to the given directory.
+pub fn load_files(params: &mut ModelParams, base_dir: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let resolve_path = |path: &str| -> String {
+        base_dir
+            .unwrap_or(Path::new(""))
+            .join(path)
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    if let Some(file) = params.initial_image.take() {
+        let file = resolve_path(&file);
+        params.initial_image_data = Some(read_image(&file).map_err(|error| {
+            io::Error::other(format!("Failed to read initial image {}: {}", file, error))
+        })?);
+    }
+    for (specie_name, specie_params) in params.species.iter_mut() {
+        for rate in [&mut specie_params.birth_rate, &mut specie_params.death_rate].iter_mut() {
+            if let RateParams::Field(RateFieldParams::Image { file, image, .. }) = rate {
+                if let Some(file) = file.take() {
+                    let file = resolve_path(&file);
+                    *image = Some(read_image(&file).map_err(|error| {
+                        io::Error::other(format!(
+                            "Failed to read rate image {} for species {}: {}",
+                            file, specie_name, error
+                        ))
+                    })?);
+                }
+            }
+        }
+        for schedule in [
+            &mut specie_params.birth_rate_schedule,
+            &mut specie_params.death_rate_schedule,
+        ]
+        .iter_mut()
+        {
+            if let Some(ScheduleParams::TimeSeries { file, points, .. }) = schedule {
+                if let Some(file) = file.take() {
+                    *points = read_time_series(&resolve_path(&file))?;
+                }
+            }
+        }
+    }
+
+    if let Some(terrain) = &mut params.terrain {
+        if let Some(map_file) = terrain.map_file.take() {
+            let map_path = base_dir.unwrap_or(Path::new("")).join(&map_file);
+            let map_contents = fs::read_to_string(&map_path).map_err(|error| {
+                io::Error::other(format!(
+                    "Failed to read terrain map {}: {}",
+                    map_path.display(),
+                    error
+                ))
+            })?;
+            terrain.map = Some(
+                map_contents
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(|line| line.to_string())
+                    .collect(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Reads a CSV file with a tick and a factor column. A header line is skipped.
+fn read_time_series(file_path: &str) -> Result<Vec<SchedulePoint>, Box<dyn Error>> {
+    let contents = fs::read_to_string(file_path).map_err(|error| {
+        io::Error::other(format!(
+            "Failed to read time series {}: {}",
+            file_path, error
+        ))
+    })?;
+    let mut points = vec![];
 
+    for (line_index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let columns: Vec<&str> = line.split(',').map(|column| column.trim()).collect();
+
+        match (
+            columns[0].parse::<usize>(),
+            columns.get(1).map(|column| column.parse::<f32>()),
+        ) {
+            (Ok(tick), Some(Ok(factor))) => points.push(SchedulePoint { tick, factor }),
+            _ if line_index == 0 => {} // Header
+            _ => {
+                return Err(Box::new(io::Error::other(format!(
+                    "Invalid line {} in time series {}: {}",
+                    line_index + 1,
+                    file_path,
+                    line
+                ))))
+            }
+        }
+    }
+
+    Ok(points)
+}
+
+// Checks that the species referred to in the model parameters exist.
+pub fn validate_params(params: &ModelParams) -> Result<(), Box<dyn Error>> {
     for (specie_name, specie_params) in params.species.iter() {
         for energy_source in specie_params
             .energy_sources
@@ -107,16 +748,473 @@ pub fn params_from_file(file_path: &str) -> Result<ModelParams, Box<dyn Error>>
             .iter()
         {
             if !params.species.contains_key(energy_source) {
-                return Err(Box::new(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!(
-                        "Energy source {} for species {} does not exist",
-                        energy_source, specie_name
-                    ),
+                return Err(Box::new(io::Error::other(format!(
+                    "Energy source {} for species {} does not exist",
+                    energy_source, specie_name
+                ))));
+            }
+        }
+
+        // Energy is only tracked for species with a metabolic cost, other energy parameters would be ignored
+        if !specie_params.uses_energy() {
+            let energy_fields = [
+                ("energy_per_prey", specie_params.energy_per_prey),
+                ("initial_energy", specie_params.initial_energy),
+                ("starvation_threshold", specie_params.starvation_threshold),
+            ];
+            for (field, value) in energy_fields.iter() {
+                if value.is_some() {
+                    return Err(Box::new(io::Error::other(format!(
+                        "Species {} sets {} without metabolic_cost",
+                        specie_name, field
+                    ))));
+                }
+            }
+        }
+    }
+
+    if let ModelType::Custom = params.model {
+        if params.rules.is_empty() {
+            return Err(Box::new(io::Error::other(
+                "Custom model requires at least one transition rule",
+            )));
+        }
+    }
+
+    let [west, north, east, south] = params.boundary.edges();
+    for mode in [west, north, east, south].iter() {
+        if let BoundaryMode::Fixed(specie_name) = mode {
+            if !params.species.contains_key(specie_name) {
+                return Err(Box::new(io::Error::other(format!(
+                    "Species {} used in boundary does not exist",
+                    specie_name
+                ))));
+            }
+        }
+    }
+    let is_periodic = |mode: &BoundaryMode| matches!(mode, BoundaryMode::Periodic);
+    if is_periodic(west) != is_periodic(east) || is_periodic(north) != is_periodic(south) {
+        return Err(Box::new(io::Error::other(
+            "Periodic boundaries must be used on both opposite edges",
+        )));
+    }
+    if params.topology == Topology::Hexagonal
+        && is_periodic(north)
+        && !params.grid_size.h.is_multiple_of(2)
+    {
+        return Err(Box::new(io::Error::other(
+            "A hexagonal grid with periodic north and south boundaries must have an even height",
+        )));
+    }
+
+    let neighborhoods = &params.neighborhoods;
+    for neighborhood in [
+        &neighborhoods.interaction,
+        &neighborhoods.feeding,
+        &neighborhoods.reproduction,
+        &neighborhoods.movement,
+    ]
+    .iter()
+    {
+        if let Some(NeighborhoodParams {
+            shape: NeighborhoodShape::Kernel(rows),
+            ..
+        }) = neighborhood
+        {
+            let is_valid = rows.len() % 2 == 1
+                && rows.iter().all(|row| {
+                    row.len() == rows.len() && row.chars().all(|c| c == '0' || c == '1')
+                });
+            if !is_valid {
+                return Err(Box::new(io::Error::other(
+                    "Neighborhood kernel must be a square mask of 0s and 1s with an odd size",
                 )));
             }
         }
     }
 
-    Ok(params)
+    // The PPPE model's movement neighborhood defaults to a Moore neighborhood with the sense radius
+    let movement_neighborhood = neighborhoods
+        .movement
+        .clone()
+        .unwrap_or_else(|| NeighborhoodParams::moore(params.sense_radius));
+    for (phase, neighborhood) in [
+        ("interaction", neighborhoods.interaction.as_ref()),
+        ("feeding", neighborhoods.feeding.as_ref()),
+        ("reproduction", neighborhoods.reproduction.as_ref()),
+        ("movement", Some(&movement_neighborhood)),
+    ]
+    .iter()
+    {
+        if let Some(neighborhood) = neighborhood {
+            if Neighborhood::new(params.topology, neighborhood)
+                .get_offsets(None)
+                .is_empty()
+            {
+                return Err(Box::new(io::Error::other(format!(
+                    "The {} neighborhood must contain at least one cell besides the center",
+                    phase
+                ))));
+            }
+        }
+    }
+
+    for (region_name, region) in params.regions.iter() {
+        if !region.is_valid() {
+            return Err(Box::new(io::Error::other(format!(
+                "Region {} must lie within the grid (0.0 to 1.0)",
+                region_name
+            ))));
+        }
+    }
+    for event in params.events.iter() {
+        let (specie_name, region_name) = match &event.action {
+            EventAction::Cull { specie, region, .. } => (Some(specie), region.as_ref()),
+            EventAction::Introduce { specie, region, .. } => (Some(specie), region.as_ref()),
+            EventAction::Clear { region } => (None, Some(region)),
+        };
+
+        if event.tick == 0 {
+            return Err(Box::new(io::Error::other(
+                "Events must take place at tick 1 or later",
+            )));
+        }
+        if let Some(specie_name) = specie_name {
+            if !params.species.contains_key(specie_name) {
+                return Err(Box::new(io::Error::other(format!(
+                    "Species {} of event at tick {} does not exist",
+                    specie_name, event.tick
+                ))));
+            }
+        }
+        if let Some(region_name) = region_name {
+            if !params.regions.contains_key(region_name) {
+                return Err(Box::new(io::Error::other(format!(
+                    "Region {} of event at tick {} does not exist",
+                    region_name, event.tick
+                ))));
+            }
+        }
+        if let EventAction::Cull { fraction, .. } = event.action {
+            if !(0.0..=1.0).contains(&fraction) {
+                return Err(Box::new(io::Error::other(format!(
+                    "Fraction of cull event at tick {} must be between 0.0 and 1.0",
+                    event.tick
+                ))));
+            }
+        }
+    }
+    for (specie_name, specie_params) in params.species.iter() {
+        for rate in [&specie_params.birth_rate, &specie_params.death_rate].iter() {
+            match rate {
+                RateParams::Field(RateFieldParams::Image {
+                    file: None,
+                    image: None,
+                    ..
+                }) => {
+                    return Err(Box::new(io::Error::other(format!(
+                        "Rate image for species {} has no file",
+                        specie_name
+                    ))));
+                }
+                RateParams::Field(RateFieldParams::Regions { values, .. }) => {
+                    for region_name in values.keys() {
+                        if !params.regions.contains_key(region_name) {
+                            return Err(Box::new(io::Error::other(format!(
+                                "Region {} used in rate of species {} does not exist",
+                                region_name, specie_name
+                            ))));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for schedule in [
+            &specie_params.birth_rate_schedule,
+            &specie_params.death_rate_schedule,
+        ]
+        .iter()
+        {
+            let is_valid = match schedule {
+                Some(ScheduleParams::Piecewise { ranges, period }) => {
+                    period.map(|period| period > 0).unwrap_or(true)
+                        && ranges.iter().all(|range| range.from < range.to)
+                }
+                Some(ScheduleParams::Sinusoidal { period, .. }) => *period > 0.0,
+                Some(ScheduleParams::TimeSeries { points, period, .. }) => {
+                    period.map(|period| period > 0).unwrap_or(true)
+                        && !points.is_empty()
+                        && points.windows(2).all(|pair| pair[0].tick < pair[1].tick)
+                }
+                None => true,
+            };
+            if !is_valid {
+                return Err(Box::new(io::Error::other(format!(
+                        "Invalid rate schedule for species {}: periods must be positive, ranges must not be empty and time series points must be in increasing order of ticks",
+                        specie_name
+                    ))));
+            }
+        }
+
+        for placement in specie_params.placement.as_ref().unwrap_or(&vec![]).iter() {
+            let (is_valid, density) = match *placement {
+                Placement::Blobs {
+                    radius, density, ..
+                } => (radius > 0.0, density),
+                Placement::Rectangle {
+                    x,
+                    y,
+                    w,
+                    h,
+                    density,
+                } => (RegionParams { x, y, w, h }.is_valid(), density),
+                Placement::Stripes { width, density, .. } => (width > 0, density),
+                Placement::Checkerboard { size, density, .. } => (size > 0, density),
+            };
+            if !is_valid || !(0.0..=1.0).contains(&density) {
+                return Err(Box::new(io::Error::other(format!(
+                        "Invalid placement for species {}: sizes must be positive, rectangles must lie within the grid (0.0 to 1.0) and densities must be between 0.0 and 1.0",
+                        specie_name
+                    ))));
+            }
+        }
+    }
+
+    for (habitat_name, habitat_params) in params.habitats.iter() {
+        for specie_name in habitat_params.species.as_ref().unwrap_or(&vec![]).iter() {
+            if !params.species.contains_key(specie_name) {
+                return Err(Box::new(io::Error::other(format!(
+                    "Species {} used in habitat {} does not exist",
+                    specie_name, habitat_name
+                ))));
+            }
+        }
+    }
+    if let Some(terrain) = &params.terrain {
+        let is_valid = match &terrain.map {
+            Some(rows) => {
+                !rows.is_empty()
+                    && rows[0].chars().count() > 0
+                    && rows
+                        .iter()
+                        .all(|row| row.chars().count() == rows[0].chars().count())
+            }
+            None => false,
+        };
+        if !is_valid {
+            return Err(Box::new(io::Error::other(
+                "Terrain map must consist of one or more rows of equal length",
+            )));
+        }
+        for habitat_name in terrain.legend.values() {
+            if !params.habitats.contains_key(habitat_name) {
+                return Err(Box::new(io::Error::other(format!(
+                    "Habitat {} used in terrain does not exist",
+                    habitat_name
+                ))));
+            }
+        }
+    }
+
+    if params.initial_image.is_some() || params.initial_image_data.is_some() {
+        let colors: Vec<&String> = params
+            .species
+            .values()
+            .filter_map(|specie_params| specie_params.color.as_ref())
+            .chain(
+                params
+                    .habitats
+                    .values()
+                    .filter_map(|habitat_params| habitat_params.color.as_ref()),
+            )
+            .collect();
+        for (index, color) in colors.iter().enumerate() {
+            if parse_hex_color(color).is_none() {
+                return Err(Box::new(io::Error::other(format!(
+                    "Color {} is not in hex format (e.g. FF0000)",
+                    color
+                ))));
+            }
+            if colors[..index]
+                .iter()
+                .any(|other_color| parse_hex_color(other_color) == parse_hex_color(color))
+            {
+                return Err(Box::new(io::Error::other(format!(
+                    "Color {} is used more than once, so the initial image is ambiguous",
+                    color
+                ))));
+            }
+        }
+    }
+
+    for rule in params.rules.iter() {
+        if rule
+            .outcomes
+            .iter()
+            .any(|outcome| !(0.0..=1.0).contains(&outcome.probability))
+        {
+            return Err(Box::new(io::Error::other(
+                "Outcome probabilities of transition rules must be between 0.0 and 1.0",
+            )));
+        }
+        // Allow for rounding errors in the sum of the probabilities
+        let total_probability: f32 = rule
+            .outcomes
+            .iter()
+            .map(|outcome| outcome.probability)
+            .sum();
+        if total_probability > 1.0 + 1e-6 {
+            return Err(Box::new(io::Error::other(format!(
+                "Outcome probabilities of a transition rule must add up to at most 1.0, not {}",
+                total_probability
+            ))));
+        }
+
+        let mut specie_names = vec![];
+        if let CellMatcher::Specie(specie_name) = &rule.from {
+            specie_names.push(specie_name);
+        }
+        for outcome in rule.outcomes.iter() {
+            if let CellOutcome::Specie(specie_name) = &outcome.to {
+                specie_names.push(specie_name);
+            }
+        }
+
+        for specie_name in specie_names {
+            if !params.species.contains_key(specie_name) {
+                return Err(Box::new(io::Error::other(format!(
+                    "Species {} used in transition rule does not exist",
+                    specie_name
+                ))));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a valid PPPE config with the given extra top-level fields.
+    fn config_json(extra_fields: &str) -> String {
+        config_json_with_fish("", extra_fields)
+    }
+
+    /// Returns a minimal config, with the given extra fields added to the only species and to the config itself.
+    fn config_json_with_fish(extra_fish_fields: &str, extra_fields: &str) -> String {
+        format!(
+            r#"{{
+                "model": "PPPE",
+                "grid_size": {{ "w": 10, "h": 10 }},
+                "species": {{
+                    "Fish": {{ "initial_population": 0.1, "death_rate": 0.1, "birth_rate": 0.5 {} }}
+                }}
+                {}
+            }}"#,
+            extra_fish_fields, extra_fields
+        )
+    }
+
+    /// Returns the error message of the validation of the given config.
+    fn validation_error(json: &str) -> String {
+        match params_from_str(json) {
+            Ok(_) => panic!("config is valid"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn accepts_default_neighborhoods() {
+        assert!(params_from_str(&config_json("")).is_ok());
+    }
+
+    #[test]
+    fn rejects_empty_kernel() {
+        let json = config_json(
+            r#", "neighborhoods": { "feeding": { "shape": { "Kernel": ["000", "000", "000"] } } }"#,
+        );
+        assert!(validation_error(&json).contains("at least one cell"));
+    }
+
+    #[test]
+    fn rejects_kernel_with_only_the_center() {
+        let json = config_json(
+            r#", "neighborhoods": { "feeding": { "shape": { "Kernel": ["000", "010", "000"] } } }"#,
+        );
+        assert!(validation_error(&json).contains("at least one cell"));
+    }
+
+    #[test]
+    fn rejects_zero_radius() {
+        let json =
+            config_json(r#", "neighborhoods": { "movement": { "shape": "Moore", "radius": 0 } }"#);
+        assert!(validation_error(&json).contains("at least one cell"));
+    }
+
+    #[test]
+    fn rejects_zero_sense_radius() {
+        assert!(
+            validation_error(&config_json(r#", "sense_radius": 0"#)).contains("at least one cell")
+        );
+    }
+
+    #[test]
+    fn rejects_rule_probabilities_above_one() {
+        let json = config_json(
+            r#", "rules": [{
+                "from": "Any",
+                "outcomes": [
+                    { "to": "Empty", "probability": 0.6 },
+                    { "to": { "Specie": "Fish" }, "probability": 0.6 }
+                ]
+            }]"#,
+        );
+        assert!(validation_error(&json).contains("add up to at most 1.0"));
+    }
+
+    #[test]
+    fn accepts_per_neighbor_rule_probabilities_up_to_one() {
+        // Per-neighbor probabilities that add up to more than 1.0 at runtime are normalised by the custom model
+        let json = config_json(
+            r#", "rules": [{
+                "from": "Empty",
+                "outcomes": [
+                    { "to": "DominantPredator", "probability": 0.5, "per_neighbor": "Predators" },
+                    { "to": { "Specie": "Fish" }, "probability": 0.5, "per_neighbor": "Empty" }
+                ]
+            }]"#,
+        );
+        assert!(params_from_str(&json).is_ok());
+    }
+
+    #[test]
+    fn rejects_energy_per_prey_without_metabolic_cost() {
+        let json = config_json_with_fish(r#", "energy_per_prey": 2.0"#, "");
+        assert!(validation_error(&json).contains("energy_per_prey without metabolic_cost"));
+    }
+
+    #[test]
+    fn rejects_initial_energy_without_metabolic_cost() {
+        let json = config_json_with_fish(r#", "initial_energy": 2.0"#, "");
+        assert!(validation_error(&json).contains("initial_energy without metabolic_cost"));
+    }
+
+    #[test]
+    fn rejects_starvation_threshold_without_metabolic_cost() {
+        let json = config_json_with_fish(r#", "starvation_threshold": 0.5"#, "");
+        assert!(validation_error(&json).contains("starvation_threshold without metabolic_cost"));
+    }
+
+    #[test]
+    fn accepts_energy_parameters_with_metabolic_cost() {
+        let json = config_json_with_fish(
+            r#", "metabolic_cost": 0.1, "energy_per_prey": 2.0, "initial_energy": 2.0, "starvation_threshold": 0.5"#,
+            "",
+        );
+        assert!(params_from_str(&json).is_ok());
+    }
 }
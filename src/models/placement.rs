@@ -0,0 +1,225 @@
+use crate::models::params::{Placement, RegionParams};
+use crate::util::{PRng, Size};
+use rand::Rng;
+
+/// Returns the cells in which the given placement puts an animal, in row-major order.
+pub fn placement_cells(placement: &Placement, size: Size, rng: &mut PRng) -> Vec<(u32, u32)> {
+    let mut cells = vec![];
+
+    match *placement {
+        Placement::Blobs {
+            count,
+            radius,
+            density,
+        } => {
+            let centers: Vec<(f32, f32)> = (0..count)
+                .map(|_| {
+                    (
+                        rng.gen_range(0.0, size.w as f32),
+                        rng.gen_range(0.0, size.h as f32),
+                    )
+                })
+                .collect();
+
+            for y in 0..size.h {
+                for x in 0..size.w {
+                    // Probability of the nearest blob, so that overlapping blobs do not exceed the density
+                    let probability = centers
+                        .iter()
+                        .map(|(center_x, center_y)| {
+                            let distance_squared = (x as f32 + 0.5 - center_x).powi(2)
+                                + (y as f32 + 0.5 - center_y).powi(2);
+                            density * (-distance_squared / (2.0 * radius * radius)).exp()
+                        })
+                        .fold(0.0f32, f32::max);
+
+                    if probability > 0.0 && rng.gen::<f32>() < probability {
+                        cells.push((x, y));
+                    }
+                }
+            }
+        }
+        Placement::Rectangle {
+            x,
+            y,
+            w,
+            h,
+            density,
+        } => {
+            let (x_range, y_range) = RegionParams { x, y, w, h }.cell_ranges(size);
+
+            for cell_y in y_range {
+                for cell_x in x_range.clone() {
+                    if rng.gen::<f32>() < density {
+                        cells.push((cell_x, cell_y));
+                    }
+                }
+            }
+        }
+        Placement::Stripes {
+            width,
+            spacing,
+            vertical,
+            density,
+        } => {
+            for y in 0..size.h {
+                for x in 0..size.w {
+                    let position = if vertical { x } else { y };
+                    if position % (width + spacing) < width && rng.gen::<f32>() < density {
+                        cells.push((x, y));
+                    }
+                }
+            }
+        }
+        Placement::Checkerboard {
+            size: square_size,
+            odd,
+            density,
+        } => {
+            for y in 0..size.h {
+                for x in 0..size.w {
+                    let is_odd = (x / square_size + y / square_size) % 2 == 1;
+                    if is_odd == odd && rng.gen::<f32>() < density {
+                        cells.push((x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn cells(placement: Placement, size: Size) -> Vec<(u32, u32)> {
+        let mut rng = PRng::seed_from_u64(1);
+        placement_cells(&placement, size, &mut rng)
+    }
+
+    #[test]
+    fn rectangle_fills_region() {
+        let placement = Placement::Rectangle {
+            x: 0.25,
+            y: 0.5,
+            w: 0.5,
+            h: 0.25,
+            density: 1.0,
+        };
+        let cells = cells(placement, Size::new(8, 8));
+
+        let mut expected = vec![];
+        for y in 4..6 {
+            for x in 2..6 {
+                expected.push((x, y));
+            }
+        }
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn rectangle_with_density_stays_within_region() {
+        let placement = Placement::Rectangle {
+            x: 0.0,
+            y: 0.0,
+            w: 0.5,
+            h: 1.0,
+            density: 0.5,
+        };
+        let cells = cells(placement, Size::new(40, 40));
+
+        assert!(cells.iter().all(|&(x, _)| x < 20));
+        // 800 cells in the region, of which about half are occupied
+        assert!(cells.len() > 300 && cells.len() < 500);
+    }
+
+    #[test]
+    fn stripes_fill_rows() {
+        let placement = Placement::Stripes {
+            width: 2,
+            spacing: 3,
+            vertical: false,
+            density: 1.0,
+        };
+        let cells = cells(placement, Size::new(4, 10));
+
+        assert_eq!(cells.len(), 4 * 4);
+        assert!(cells.iter().all(|&(_, y)| y % 5 < 2));
+    }
+
+    #[test]
+    fn vertical_stripes_fill_columns() {
+        let placement = Placement::Stripes {
+            width: 1,
+            spacing: 1,
+            vertical: true,
+            density: 1.0,
+        };
+        let cells = cells(placement, Size::new(6, 3));
+
+        assert_eq!(cells.len(), 3 * 3);
+        assert!(cells.iter().all(|&(x, _)| x % 2 == 0));
+    }
+
+    #[test]
+    fn checkerboard_fills_even_or_odd_squares() {
+        for &odd in &[false, true] {
+            let placement = Placement::Checkerboard {
+                size: 2,
+                odd,
+                density: 1.0,
+            };
+            let cells = cells(placement, Size::new(8, 8));
+
+            assert_eq!(cells.len(), 32);
+            assert!(cells
+                .iter()
+                .all(|&(x, y)| ((x / 2 + y / 2) % 2 == 1) == odd));
+        }
+    }
+
+    #[test]
+    fn checkerboard_with_density_places_fraction_of_squares() {
+        let placement = Placement::Checkerboard {
+            size: 1,
+            odd: false,
+            density: 0.25,
+        };
+        let cells = cells(placement, Size::new(40, 40));
+
+        assert!(cells.iter().all(|&(x, y)| (x + y) % 2 == 0));
+        // 800 even cells, of which about a quarter are occupied
+        assert!(cells.len() > 140 && cells.len() < 260);
+    }
+
+    #[test]
+    fn blob_stays_close_to_its_center() {
+        let placement = Placement::Blobs {
+            count: 1,
+            radius: 2.0,
+            density: 1.0,
+        };
+        let cells = cells(placement, Size::new(64, 64));
+
+        // A Gaussian blob with density 1 covers about 2 * pi * radius^2 cells
+        assert!(cells.len() > 10 && cells.len() < 50);
+        let min_x = cells.iter().map(|&(x, _)| x).min().unwrap();
+        let max_x = cells.iter().map(|&(x, _)| x).max().unwrap();
+        let min_y = cells.iter().map(|&(_, y)| y).min().unwrap();
+        let max_y = cells.iter().map(|&(_, y)| y).max().unwrap();
+        assert!(max_x - min_x <= 16 && max_y - min_y <= 16);
+    }
+
+    #[test]
+    fn no_blobs_place_nothing() {
+        let placement = Placement::Blobs {
+            count: 0,
+            radius: 2.0,
+            density: 1.0,
+        };
+        assert!(cells(placement, Size::new(16, 16)).is_empty());
+    }
+}
@@ -7,56 +7,98 @@ pub struct PPPEModel {
     grid: Grid,
     params: ModelParams,
     rng: PRng,
+    seed: u64, // Seed that the random number generator was created with
+    feeding_neighborhood: Neighborhood,
+    reproduction_neighborhood: Neighborhood,
+    movement_neighborhood: Neighborhood,
+    rates: Rates,
+    ticks_elapsed: usize, // Number of ticks since the model was created, used to evaluate the rate schedules
 }
 
 impl PPPEModel {
-    pub fn new(params: ModelParams) -> PPPEModel {
-        let grid = Grid::new(params.grid_size);
-        let rng = PRng::seed_from_u64(params.random_seed.unwrap_or(time_ns() as u64));
-
-        PPPEModel { grid, params, rng }
+    pub fn new(params: ModelParams) -> Result<PPPEModel, Box<dyn Error>> {
+        let grid = Grid::from_params(&params)?;
+        let feeding_neighborhood = Neighborhood::new(
+            params.topology,
+            params
+                .neighborhoods
+                .feeding
+                .as_ref()
+                .unwrap_or(&NeighborhoodParams::von_neumann(1)),
+        );
+        let reproduction_neighborhood = Neighborhood::new(
+            params.topology,
+            params
+                .neighborhoods
+                .reproduction
+                .as_ref()
+                .unwrap_or(&NeighborhoodParams::von_neumann(1)),
+        );
+        let movement_neighborhood = Neighborhood::new(
+            params.topology,
+            params
+                .neighborhoods
+                .movement
+                .as_ref()
+                .unwrap_or(&NeighborhoodParams::moore(params.sense_radius)),
+        );
+        let seed = params.random_seed.unwrap_or(time_ns() as u64);
+        let rng = PRng::seed_from_u64(seed);
+        let rates = Rates::from_params(&params)?;
+
+        Ok(PPPEModel {
+            grid,
+            params,
+            rng,
+            seed,
+            feeding_neighborhood,
+            reproduction_neighborhood,
+            movement_neighborhood,
+            rates,
+            ticks_elapsed: 0,
+        })
     }
 
     fn feeding_phase_next_cell_state(
-        &mut self,
+        &self,
+        x: u32,
+        y: u32,
         cell: &Cell,
-        neighbors: &Vec<Cell>,
+        neighbors: &[Cell],
+        rng: &mut PRng,
     ) -> (Cell, bool) {
         match cell {
-            Cell::Animal(specie_id) => {
-                let (n_predators, _) =
-                    get_neighbor_predators(cell, neighbors, &self.params, &mut self.rng);
+            Cell::Animal(specie_id, _) => {
+                let (n_predators, _) = get_neighbor_predators(cell, neighbors, &self.params, rng);
                 if self.params.is_specie_herbivore(*specie_id) || n_predators > 0 {
                     // Cell is prey
-                    let random = self.rng.gen::<f32>();
-                    let prey_death_rate = self.params.get_specie_by_id(*specie_id).death_rate;
+                    let random = rng.gen::<f32>();
+                    let prey_death_rate = self.rates.death_rate(*specie_id, x, y);
 
                     if random < (1.0f32 - prey_death_rate).powf(n_predators as f32) {
                         // Hunt failed/no predators, cell stays prey
-                        (Cell::Animal(*specie_id), false)
+                        (cell.clone(), false)
                     } else {
                         // Cell becomes empty due to kill
                         (Cell::Empty, true)
                     }
                 } else {
                     // Cell is predator
-                    let random = self.rng.gen::<f32>();
+                    let random = rng.gen::<f32>();
                     let (n_prey, most_occurring_prey_id) =
-                        get_neighbor_prey(cell, neighbors, &self.params, &mut self.rng);
+                        get_neighbor_prey(cell, neighbors, &self.params, rng);
                     let prey_death_rate = if n_prey == 0 {
                         0.0
                     } else {
-                        self.params
-                            .get_specie_by_id(most_occurring_prey_id)
-                            .death_rate
+                        self.rates.death_rate(most_occurring_prey_id, x, y)
                     };
 
                     if random < (1.0f32 - prey_death_rate).powf(n_prey as f32) {
                         // Hunt fails, predator stays unfed.
-                        (Cell::Animal(*specie_id), false)
+                        (cell.clone(), false)
                     } else {
                         // Hunt succeeded, predator gets fed.
-                        (Cell::Animal(*specie_id), true)
+                        (cell.clone(), true)
                     }
                 }
             }
@@ -65,49 +107,71 @@ impl PPPEModel {
     }
 
     fn feeding_phase(&mut self) -> (Grid, Vec<bool>) {
-        let grid_size = self.grid.get_size();
-        let mut new_cells = Grid::new(grid_size);
-        let mut cells_fed_or_killed = vec![];
-
-        for x in 0..grid_size.w {
-            for y in 0..grid_size.h {
-                let cell = self.grid.get_cell_at(x, y).clone();
-                let neighbors = self.grid.von_neumann_neighborhood_r1(x, y);
-                let (new_cell, fed_or_killed) =
-                    self.feeding_phase_next_cell_state(&cell, &neighbors);
-                new_cells.set_cell_at(x, y, new_cell);
-                cells_fed_or_killed.push(fed_or_killed);
-            }
-        }
-
-        (new_cells, cells_fed_or_killed)
+        let phase_seed = self.rng.gen::<u64>();
+        let model = &*self;
+
+        let (new_cells, cells_fed_or_killed) = map_cells(
+            self.grid.get_size(),
+            phase_seed,
+            thread_count(self.params.threads),
+            |x, y, rng| {
+                let cell = model.grid.get_cell_at(x, y).clone();
+                let neighbors = model
+                    .grid
+                    .neighborhood(x, y, &model.feeding_neighborhood, None);
+                model.feeding_phase_next_cell_state(x, y, &cell, &neighbors, rng)
+            },
+        )
+        .into_iter()
+        .unzip();
+
+        (self.grid.with_cells(new_cells), cells_fed_or_killed)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn reproduction_phase_next_cell_state(
-        &mut self,
+        &self,
+        x: u32,
+        y: u32,
         cell: &Cell,
         fed_or_killed: bool,
-        neighbors: &Vec<Cell>,
-        neighbor_cells_fed_or_killed: &Vec<bool>,
+        neighbors: &[Cell],
+        neighbor_cells_fed_or_killed: &[bool],
+        rng: &mut PRng,
     ) -> Cell {
         match cell {
-            Cell::Animal(specie_id) => {
-                let (n_predators, _) =
-                    get_neighbor_predators(cell, neighbors, &self.params, &mut self.rng);
-                if self.params.is_specie_herbivore(*specie_id) || n_predators > 0 {
-                    // Cell is herbivore, stays herbivore
-                    Cell::Animal(*specie_id)
+            &Cell::Animal(specie_id, state) => {
+                let (n_predators, _) = get_neighbor_predators(cell, neighbors, &self.params, rng);
+                let specie_is_herbivore = self.params.is_specie_herbivore(specie_id);
+                if specie_is_herbivore || n_predators > 0 {
+                    // Cell is herbivore, stays herbivore unless it dies of starvation or old age
+                    match live_one_step(
+                        specie_id,
+                        state,
+                        specie_is_herbivore || fed_or_killed,
+                        &self.params,
+                    ) {
+                        Some(state) => Cell::Animal(specie_id, state),
+                        None => Cell::Empty,
+                    }
                 } else {
                     // Cell is a predator
-                    let random = self.rng.gen::<f32>();
-                    let death_rate = self.params.get_specie_by_id(*specie_id).death_rate;
+                    let specie_params = self.params.get_specie_by_id(specie_id);
+                    if !specie_params.uses_energy() {
+                        let random = rng.gen::<f32>();
+                        let death_rate = self.rates.death_rate(specie_id, x, y);
+
+                        if random < death_rate {
+                            // The predator dies, the cell is now empty.
+                            return Cell::Empty;
+                        }
+                    }
 
-                    if random < death_rate {
-                        // The predator dies, the cell is now empty.
-                        Cell::Empty
-                    } else {
+                    match live_one_step(specie_id, state, fed_or_killed, &self.params) {
                         // The predator lives.
-                        Cell::Animal(*specie_id)
+                        Some(state) => Cell::Animal(specie_id, state),
+                        // The predator dies of starvation or old age, the cell is now empty.
+                        None => Cell::Empty,
                     }
                 }
             }
@@ -115,21 +179,19 @@ impl PPPEModel {
                 if !fed_or_killed {
                     // Cell was already empty
                     let (n_herbivores, most_occurring_herbivore_id) =
-                        get_neighbor_herbivores(neighbors, &self.params, &mut self.rng);
-                    let (n_predators, most_occurring_predator_id) =
-                        get_neighbor_predators(cell, neighbors, &self.params, &mut self.rng);
+                        get_neighbor_herbivores(neighbors, &self.params, rng);
+                    let (n_predators, _) =
+                        get_neighbor_predators(cell, neighbors, &self.params, rng);
                     if n_herbivores == 0 || n_predators > 0 {
                         // Cell remains empty
                         Cell::Empty
                     } else {
-                        let prey_birth_rate = self
-                            .params
-                            .get_specie_by_id(most_occurring_herbivore_id)
-                            .birth_rate;
-                        let random = self.rng.gen::<f32>();
+                        let prey_birth_rate =
+                            self.rates.birth_rate(most_occurring_herbivore_id, x, y);
+                        let random = rng.gen::<f32>();
                         if random < (1.0f32 - prey_birth_rate).powf(n_herbivores as f32) {
                             // Cell becomes prey by breeding
-                            Cell::Animal(most_occurring_herbivore_id)
+                            newborn_animal(most_occurring_herbivore_id, &self.params)
                         } else {
                             // Cell remains empty
                             Cell::Empty
@@ -144,233 +206,217 @@ impl PPPEModel {
                         }
                     }
 
-                    let (n_fed_predators, most_occurring_predator_id) = get_neighbor_predators(
-                        cell,
-                        &fed_or_killed_neighbors,
-                        &self.params,
-                        &mut self.rng,
-                    );
+                    let (n_fed_predators, most_occurring_predator_id) =
+                        get_neighbor_predators(cell, &fed_or_killed_neighbors, &self.params, rng);
                     let predator_birth_rate = if n_fed_predators == 0 {
                         0.0
                     } else {
-                        self.params
-                            .get_specie_by_id(most_occurring_predator_id)
-                            .birth_rate
+                        self.rates.birth_rate(most_occurring_predator_id, x, y)
                     };
 
-                    let random = self.rng.gen::<f32>();
+                    let random = rng.gen::<f32>();
 
                     if random < (1.0f32 - predator_birth_rate).powf(n_fed_predators as f32) {
                         // No reproduction occurs, cell remains empty
                         Cell::Empty
                     } else {
                         // Cell becomes predator
-                        Cell::Animal(most_occurring_predator_id)
+                        newborn_animal(most_occurring_predator_id, &self.params)
                     }
                 }
             }
         }
     }
 
-    fn reproduction_phase(&mut self, fed_cells: &Grid, cells_fed_or_killed: &Vec<bool>) -> Grid {
+    fn reproduction_phase(&mut self, fed_cells: &Grid, cells_fed_or_killed: &[bool]) -> Grid {
         let grid_size = fed_cells.get_size();
-        let mut new_cells = Grid::new(grid_size);
-
-        for x in 0..grid_size.w {
-            for y in 0..grid_size.h {
+        let phase_seed = self.rng.gen::<u64>();
+        let model = &*self;
+
+        let new_cells = map_cells(
+            grid_size,
+            phase_seed,
+            thread_count(self.params.threads),
+            |x, y, rng| {
                 let cell = fed_cells.get_cell_at(x, y).clone();
                 let fed_or_killed = cells_fed_or_killed[(y * grid_size.w + x) as usize];
-                let neighbors = self.grid.von_neumann_neighborhood_r1(x, y);
+                let reproduction_neighbors =
+                    model
+                        .grid
+                        .neighbors(x, y, &model.reproduction_neighborhood, None);
+                let neighbors: Vec<Cell> = reproduction_neighbors
+                    .iter()
+                    .map(|neighbor| model.grid.get_neighbor_cell(neighbor))
+                    .collect();
 
-                let mut neighbor_cells_fed_or_killed: Vec<bool> = vec![];
-                if x > 0 {
-                    neighbor_cells_fed_or_killed
-                        .push(cells_fed_or_killed[(y * grid_size.w + x - 1) as usize]);
-                }
-                if y > 0 {
-                    neighbor_cells_fed_or_killed
-                        .push(cells_fed_or_killed[((y - 1) * grid_size.w + x) as usize]);
-                }
-                if x < grid_size.w - 1 {
-                    neighbor_cells_fed_or_killed
-                        .push(cells_fed_or_killed[(y * grid_size.w + x + 1) as usize]);
-                }
-                if y < grid_size.h - 1 {
-                    neighbor_cells_fed_or_killed
-                        .push(cells_fed_or_killed[((y + 1) * grid_size.w + x) as usize]);
-                }
+                let neighbor_cells_fed_or_killed: Vec<bool> = reproduction_neighbors
+                    .iter()
+                    .map(|neighbor| match neighbor {
+                        &Neighbor::Inside(neighbor_x, neighbor_y) => {
+                            cells_fed_or_killed[(neighbor_y * grid_size.w + neighbor_x) as usize]
+                        }
+                        Neighbor::Outside(_) => false,
+                    })
+                    .collect();
 
-                let new_cell = self.reproduction_phase_next_cell_state(
+                model.reproduction_phase_next_cell_state(
+                    x,
+                    y,
                     &cell,
                     fed_or_killed,
                     &neighbors,
                     &neighbor_cells_fed_or_killed,
-                );
-                new_cells.set_cell_at(x, y, new_cell);
-            }
-        }
+                    rng,
+                )
+            },
+        );
 
-        new_cells
+        self.grid.with_cells(new_cells)
     }
 
-    fn movement_phase(&mut self, cells: &Grid) -> Grid {
-        let grid_size = self.grid.get_size();
-        let quadrants = vec![
-            Quadrant::East,
-            Quadrant::North,
-            Quadrant::West,
-            Quadrant::South,
-        ];
-
-        let mut competition_list: Vec<(u32, u32, u32, u32)> = vec![];
+    /// Determines the cell that the animal at (x, y) intends to move to, if any.
+    fn movement_intent(
+        &self,
+        x: u32,
+        y: u32,
+        quadrants: &[Quadrant],
+        rng: &mut PRng,
+    ) -> Option<(u32, u32)> {
+        let cell = self.grid.get_cell_at(x, y).clone();
+
+        let neighbors = self
+            .grid
+            .neighborhood(x, y, &self.movement_neighborhood, None);
+        let neighbors_by_quatrant: BTreeMap<Quadrant, Vec<Cell>> = quadrants
+            .iter()
+            .map(|quadrant| {
+                (
+                    *quadrant,
+                    self.grid
+                        .neighborhood(x, y, &self.movement_neighborhood, Some(*quadrant)),
+                )
+            })
+            .filter(|(_, neighbors)| !neighbors.is_empty()) // Do not consider quadrants that contain no cells
+            .collect();
+        let possible_quadrants: Vec<Quadrant> = neighbors_by_quatrant.keys().copied().collect();
 
-        for x in 0..grid_size.w {
-            for y in 0..grid_size.h {
-                let cell = self.grid.get_cell_at(x, y).clone();
-
-                let neighbors = self
-                    .grid
-                    .moore_neighborhood(x, y, self.params.sense_radius, None);
-                let neighbors_by_quatrant: BTreeMap<Quadrant, Vec<Cell>> = quadrants
-                    .iter()
-                    .map(|quadrant| {
-                        (
-                            *quadrant,
-                            self.grid.moore_neighborhood(
-                                x,
-                                y,
-                                self.params.sense_radius,
-                                Some(*quadrant),
-                            ),
-                        )
-                    })
-                    .filter(|(_, neighbors)| neighbors.len() > 0) // Do not consider quadrants that contain no cells
-                    .collect();
-                let possible_quadrants: Vec<Quadrant> = neighbors_by_quatrant
-                    .keys()
-                    .map(|quadrant| *quadrant)
-                    .collect();
+        match cell {
+            Cell::Animal(specie_id, _) => {
+                let (n_predators, _) = get_neighbor_predators(&cell, &neighbors, &self.params, rng);
+                // Draws from the random stream like the other lookups, even though the result is not used
+                get_neighbor_prey(&cell, &neighbors, &self.params, rng);
 
-                match cell {
-                    Cell::Animal(specie_id) => {
-                        let (n_predators, most_occurring_predator_id) =
-                            get_neighbor_predators(&cell, &neighbors, &self.params, &mut self.rng);
-                        let (n_prey, most_occurring_prey_id) =
-                            get_neighbor_prey(&cell, &neighbors, &self.params, &mut self.rng);
-
-                        let mut intent = None;
-
-                        if n_predators > 0 {
-                            // Cell is prey
-                            let mut n_predators_by_quadrant: Vec<(Quadrant, u32)> =
-                                possible_quadrants
-                                    .iter()
-                                    .map(|quadrant| {
-                                        (
-                                            *quadrant,
-                                            get_neighbor_predators(
-                                                &cell,
-                                                &neighbors_by_quatrant[quadrant],
-                                                &self.params,
-                                                &mut self.rng,
-                                            )
-                                            .0,
-                                        )
-                                    })
-                                    .collect();
-                            n_predators_by_quadrant.sort_by(
-                                |(_, a_n_predators), (_, b_n_predators)| {
-                                    a_n_predators.partial_cmp(b_n_predators).unwrap()
-                                },
-                            );
-
-                            if n_predators_by_quadrant.len() > 0 {
-                                // Intent towards a random quadrant with the least amount of predators
-                                intent = Some(n_predators_by_quadrant[0].0);
-
-                                let n_lowest_predators = n_predators_by_quadrant[0].1;
-                                let lowest_predator_quadrants: Vec<Quadrant> =
-                                    n_predators_by_quadrant
-                                        .iter()
-                                        .filter(|(_, n_prey)| n_prey == &n_lowest_predators)
-                                        .map(|(quadrant, _)| quadrant.clone())
-                                        .collect();
-                                let random = self.rng.gen_range(0, lowest_predator_quadrants.len());
-                                intent = Some(lowest_predator_quadrants[random]);
-                            }
-                        } else if !self.params.is_specie_herbivore(specie_id) {
-                            // Cell is predator
-                            let mut n_prey_by_quadrant: Vec<(Quadrant, u32)> = possible_quadrants
-                                .iter()
-                                .map(|quadrant| {
-                                    (
-                                        *quadrant,
-                                        get_neighbor_prey(
-                                            &cell,
-                                            &neighbors_by_quatrant[quadrant],
-                                            &self.params,
-                                            &mut self.rng,
-                                        )
-                                        .0,
-                                    )
-                                })
-                                .collect();
-                            n_prey_by_quadrant.sort_by(|(_, a_n_prey), (_, b_n_prey)| {
-                                a_n_prey.partial_cmp(b_n_prey).unwrap()
-                            });
-
-                            if n_prey_by_quadrant.len() > 0 {
-                                // Intent towards a random quadrant with the most amount of prey
-                                let n_highest_prey = n_prey_by_quadrant.last().unwrap().1;
-                                let highest_prey_quadrants: Vec<Quadrant> = n_prey_by_quadrant
-                                    .iter()
-                                    .filter(|(_, n_prey)| n_prey == &n_highest_prey)
-                                    .map(|(quadrant, _)| quadrant.clone())
-                                    .collect();
-                                let random = self.rng.gen_range(0, highest_prey_quadrants.len());
-                                intent = Some(highest_prey_quadrants[random]);
-                            } else {
-                                // Choose random direction
-                                intent = Some(
-                                    possible_quadrants
-                                        [self.rng.gen_range(0, possible_quadrants.len())],
-                                );
-                            }
-                        } else {
-                            // Cell is prey, remains stationary
-                        }
+                let mut intent = None;
 
-                        match intent {
-                            Some(Quadrant::East) => {
-                                competition_list.push((x, y, x + 1, y));
-                            }
-                            Some(Quadrant::North) => {
-                                competition_list.push((x, y, x, y - 1));
-                            }
-                            Some(Quadrant::West) => {
-                                competition_list.push((x, y, x - 1, y));
-                            }
-                            Some(Quadrant::South) => {
-                                competition_list.push((x, y, x, y + 1));
-                            }
-                            None => {}
-                        }
+                if n_predators > 0 {
+                    // Cell is prey
+                    let mut n_predators_by_quadrant: Vec<(Quadrant, u32)> = possible_quadrants
+                        .iter()
+                        .map(|quadrant| {
+                            (
+                                *quadrant,
+                                get_neighbor_predators(
+                                    &cell,
+                                    &neighbors_by_quatrant[quadrant],
+                                    &self.params,
+                                    rng,
+                                )
+                                .0,
+                            )
+                        })
+                        .collect();
+                    n_predators_by_quadrant.sort_by(|(_, a_n_predators), (_, b_n_predators)| {
+                        a_n_predators.partial_cmp(b_n_predators).unwrap()
+                    });
+
+                    if !n_predators_by_quadrant.is_empty() {
+                        // Intent towards a random quadrant with the least amount of predators
+                        let n_lowest_predators = n_predators_by_quadrant[0].1;
+                        let lowest_predator_quadrants: Vec<Quadrant> = n_predators_by_quadrant
+                            .iter()
+                            .filter(|(_, n_prey)| n_prey == &n_lowest_predators)
+                            .map(|(quadrant, _)| *quadrant)
+                            .collect();
+                        let random = rng.gen_range(0, lowest_predator_quadrants.len());
+                        intent = Some(lowest_predator_quadrants[random]);
                     }
-                    Cell::Empty => {}
+                } else if !self.params.is_specie_herbivore(specie_id) {
+                    // Cell is predator
+                    let mut n_prey_by_quadrant: Vec<(Quadrant, u32)> = possible_quadrants
+                        .iter()
+                        .map(|quadrant| {
+                            (
+                                *quadrant,
+                                get_neighbor_prey(
+                                    &cell,
+                                    &neighbors_by_quatrant[quadrant],
+                                    &self.params,
+                                    rng,
+                                )
+                                .0,
+                            )
+                        })
+                        .collect();
+                    n_prey_by_quadrant.sort_by(|(_, a_n_prey), (_, b_n_prey)| {
+                        a_n_prey.partial_cmp(b_n_prey).unwrap()
+                    });
+
+                    if !n_prey_by_quadrant.is_empty() {
+                        // Intent towards a random quadrant with the most amount of prey
+                        let n_highest_prey = n_prey_by_quadrant.last().unwrap().1;
+                        let highest_prey_quadrants: Vec<Quadrant> = n_prey_by_quadrant
+                            .iter()
+                            .filter(|(_, n_prey)| n_prey == &n_highest_prey)
+                            .map(|(quadrant, _)| *quadrant)
+                            .collect();
+                        let random = rng.gen_range(0, highest_prey_quadrants.len());
+                        intent = Some(highest_prey_quadrants[random]);
+                    } else {
+                        // No quadrant contains any cells, e.g. when the cell is enclosed by obstacles, remains stationary
+                    }
+                } else {
+                    // Cell is prey, remains stationary
                 }
+
+                intent.and_then(|quadrant| self.grid.adjacent_coord(x, y, quadrant))
             }
+            Cell::Empty => None,
         }
+    }
+
+    fn movement_phase(&mut self, cells: &Grid) -> Grid {
+        let grid_size = self.grid.get_size();
+        let quadrants = self.grid.quadrants();
+        let phase_seed = self.rng.gen::<u64>();
+        let model = &*self;
+
+        let intents = map_cells(
+            grid_size,
+            phase_seed,
+            thread_count(self.params.threads),
+            |x, y, rng| model.movement_intent(x, y, &quadrants, rng),
+        );
+        let competition_list: Vec<(u32, u32, u32, u32)> = intents
+            .iter()
+            .enumerate()
+            .filter_map(|(i, intent)| {
+                let (x, y) = (i as u32 % grid_size.w, i as u32 / grid_size.w);
+                intent.map(|(x_to, y_to)| (x, y, x_to, y_to))
+            })
+            .collect();
 
         let mut competition_map: BTreeMap<(u32, u32), Vec<(u32, u32)>> = BTreeMap::new();
 
         for (x_from, y_from, x_to, y_to) in competition_list.iter() {
-            if cells.get_cell_at(*x_to, *y_to) == &Cell::Empty {
-                if !competition_map.contains_key(&(*x_to, *y_to)) {
-                    competition_map.insert((*x_to, *y_to), vec![]);
-                }
+            // Animals cannot move into a habitat that their specie may not occupy
+            let can_move = match cells.get_cell_at(*x_from, *y_from) {
+                &Cell::Animal(specie_id, _) => cells.can_occupy(*x_to, *y_to, specie_id),
+                Cell::Empty => true,
+            };
+            if cells.get_cell_at(*x_to, *y_to) == &Cell::Empty && can_move {
                 competition_map
-                    .get_mut(&(*x_to, *y_to))
-                    .unwrap()
+                    .entry((*x_to, *y_to))
+                    .or_default()
                     .push((*x_from, *y_from));
             }
         }
@@ -390,12 +436,12 @@ impl PPPEModel {
 }
 
 impl Model for PPPEModel {
-    fn populate(&mut self) {
-        self.grid.populate(&self.params, &mut self.rng);
+    fn populate(&mut self) -> Result<(), Box<dyn Error>> {
+        self.grid.populate(&self.params, &mut self.rng)
     }
 
     fn tick(&mut self) {
-        let grid_size = self.grid.get_size();
+        self.rates.update(self.ticks_elapsed);
 
         // Feeding phase
         let (cells_after_feed, cells_fed_or_killed) = self.feeding_phase();
@@ -404,13 +450,159 @@ impl Model for PPPEModel {
         let cells_after_movement = self.movement_phase(&cells_after_reproduction);
 
         self.grid = cells_after_movement;
+        self.ticks_elapsed += 1;
     }
 
     fn get_grid(&self) -> &Grid {
         &self.grid
     }
 
+    fn get_grid_mut(&mut self) -> &mut Grid {
+        &mut self.grid
+    }
+
     fn get_params(&self) -> &ModelParams {
         &self.params
     }
+
+    fn get_rng(&self) -> &PRng {
+        &self.rng
+    }
+
+    fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn inspect_cell(&self, x: u32, y: u32) -> Vec<String> {
+        let cell = self.grid.get_cell_at(x, y);
+        let feeding_neighbors = self
+            .grid
+            .neighborhood(x, y, &self.feeding_neighborhood, None);
+        let reproduction_neighbors =
+            self.grid
+                .neighborhood(x, y, &self.reproduction_neighborhood, None);
+        let mut lines = describe_cell(
+            &self.grid,
+            &self.params,
+            x,
+            y,
+            &[
+                ("Feeding", &feeding_neighbors),
+                ("Reproduction", &reproduction_neighbors),
+            ],
+        );
+
+        // Predators and prey per quadrant of the movement neighborhood, on which the movement decision is based
+        if let Cell::Animal(_, _) = cell {
+            let mut rng = PRng::seed_from_u64(0);
+            lines.push(String::from("Movement neighborhood"));
+            for quadrant in self.grid.quadrants() {
+                let quadrant_neighbors =
+                    self.grid
+                        .neighborhood(x, y, &self.movement_neighborhood, Some(quadrant));
+                if !quadrant_neighbors.is_empty() {
+                    let (n_predators, _) =
+                        get_neighbor_predators(cell, &quadrant_neighbors, &self.params, &mut rng);
+                    let (n_prey, _) =
+                        get_neighbor_prey(cell, &quadrant_neighbors, &self.params, &mut rng);
+                    lines.push(format!(
+                        "  {:?}: {} predators, {} prey",
+                        quadrant, n_predators, n_prey
+                    ));
+                }
+            }
+        }
+
+        lines
+    }
+
+    fn restore(&mut self, grid: Grid, rng: PRng, ticks_elapsed: usize) {
+        self.grid = grid;
+        self.rng = rng;
+        self.ticks_elapsed = ticks_elapsed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::create_model_from_str;
+    use crate::models::params::params_from_str;
+
+    /// Returns the number of cells in each phase's neighborhood of a PPPE model with the given extra fields.
+    fn phase_neighborhood_sizes(extra_fields: &str) -> (usize, usize, usize) {
+        let params = params_from_str(&format!(
+            r#"{{
+                "model": "PPPE",
+                "grid_size": {{ "w": 8, "h": 8 }},
+                "species": {{
+                    "Fish": {{ "initial_population": 0.1, "death_rate": 0.1, "birth_rate": 0.5 }}
+                }}
+                {}
+            }}"#,
+            extra_fields
+        ))
+        .unwrap();
+        let model = PPPEModel::new(params).unwrap();
+
+        (
+            model.feeding_neighborhood.get_offsets(None).len(),
+            model.reproduction_neighborhood.get_offsets(None).len(),
+            model.movement_neighborhood.get_offsets(None).len(),
+        )
+    }
+
+    #[test]
+    fn phases_use_default_neighborhoods() {
+        // Von Neumann with radius 1 for feeding and reproduction, Moore with the sense radius (2) for movement
+        assert_eq!(phase_neighborhood_sizes(""), (4, 4, 24));
+        assert_eq!(
+            phase_neighborhood_sizes(r#", "sense_radius": 1"#),
+            (4, 4, 8)
+        );
+    }
+
+    #[test]
+    fn phases_use_configured_neighborhoods() {
+        let sizes = phase_neighborhood_sizes(
+            r#", "neighborhoods": {
+                "feeding": { "shape": "Moore" },
+                "movement": { "shape": { "Kernel": ["010", "101", "010"] } }
+            }"#,
+        );
+        assert_eq!(sizes, (8, 4, 4));
+    }
+
+    #[test]
+    fn predator_enclosed_by_obstacles_stays_put() {
+        let mut model = create_model_from_str(
+            r#"{
+                "model": "PPPE",
+                "random_seed": 1,
+                "grid_size": { "w": 3, "h": 3 },
+                "habitats": { "Rock": { "obstacle": true } },
+                "terrain": { "map": ["XXX", "X.X", "XXX"], "legend": { "X": "Rock" } },
+                "species": {
+                    "Algae": { "initial_population": 0.0, "death_rate": 0.0, "birth_rate": 0.0 },
+                    "Shark": {
+                        "initial_population": 1.0,
+                        "death_rate": 0.0,
+                        "birth_rate": 0.0,
+                        "energy_sources": ["Algae"]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let shark_id = model.get_params().specie_id_from_name("Shark");
+
+        for _ in 0..10 {
+            model.tick();
+        }
+
+        assert!(matches!(
+            *model.get_grid().get_cell_at(1, 1),
+            Cell::Animal(specie_id, _) if specie_id == shark_id
+        ));
+    }
 }
@@ -0,0 +1,424 @@
+use crate::models::image::read_image;
+use crate::models::params::{ModelParams, RateFieldParams, RateParams, ScheduleParams};
+use crate::util::Size;
+use std::error::Error;
+use std::io;
+
+/// A species rate, either the same everywhere or a value per cell.
+enum Rate {
+    Constant(f32),
+    Field(Vec<f32>), // Value per cell, in row-major order
+}
+
+impl Rate {
+    fn from_params(rate_params: &RateParams, params: &ModelParams) -> Result<Rate, Box<dyn Error>> {
+        let field = match rate_params {
+            &RateParams::Constant(value) => return Ok(Rate::Constant(value)),
+            RateParams::Field(field) => field,
+        };
+        let size = params.grid_size;
+        let mut values = Vec::with_capacity(size.w as usize * size.h as usize);
+
+        match field {
+            &RateFieldParams::Gradient { from, to, vertical } => {
+                for y in 0..size.h {
+                    for x in 0..size.w {
+                        // Position of the cell's center, from 0.0 at the first edge to 1.0 at the opposite edge
+                        let t = if vertical {
+                            (y as f32 + 0.5) / size.h as f32
+                        } else {
+                            (x as f32 + 0.5) / size.w as f32
+                        };
+                        values.push(from + (to - from) * t);
+                    }
+                }
+            }
+            &RateFieldParams::Image {
+                ref file,
+                ref image,
+                min,
+                max,
+            } => {
+                // The image has been read from the file when loading the parameters, unless they were created otherwise
+                let image = match (image, file) {
+                    (Some(image), _) => image.clone(),
+                    (None, Some(file)) => read_image(file).map_err(|error| {
+                        io::Error::other(format!("Failed to read rate image {}: {}", file, error))
+                    })?,
+                    (None, None) => {
+                        return Err(Box::new(io::Error::other("Rate image has no file")))
+                    }
+                };
+
+                for y in 0..size.h {
+                    for x in 0..size.w {
+                        let color = image.color_at_cell(x, y, size);
+                        let gray = ((color >> 16) & 0xff) + ((color >> 8) & 0xff) + (color & 0xff);
+                        values.push(min + (max - min) * gray as f32 / (3.0 * 255.0));
+                    }
+                }
+            }
+            RateFieldParams::Regions {
+                default,
+                values: region_values,
+            } => {
+                values.resize(size.w as usize * size.h as usize, *default);
+
+                for (region_name, value) in region_values.iter() {
+                    let (x_range, y_range) = params.regions[region_name].cell_ranges(size);
+
+                    for y in y_range {
+                        for x in x_range.clone() {
+                            values[x as usize + y as usize * size.w as usize] = *value;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Rate::Field(values))
+    }
+
+    #[inline]
+    fn at(&self, index: usize) -> f32 {
+        match self {
+            &Rate::Constant(value) => value,
+            Rate::Field(values) => values[index],
+        }
+    }
+}
+
+/// Returns the factor that the schedule scales a rate by in the given tick.
+fn schedule_factor(schedule: &ScheduleParams, tick: usize) -> f32 {
+    match schedule {
+        ScheduleParams::Piecewise { ranges, period } => {
+            let tick = period.map(|period| tick % period).unwrap_or(tick);
+            ranges
+                .iter()
+                .find(|range| range.from <= tick && tick < range.to)
+                .map(|range| range.factor)
+                .unwrap_or(1.0)
+        }
+        &ScheduleParams::Sinusoidal {
+            period,
+            amplitude,
+            phase,
+        } => {
+            let angle = 2.0 * std::f32::consts::PI * (tick as f32 + phase) / period;
+            1.0 + amplitude * angle.sin()
+        }
+        ScheduleParams::TimeSeries { points, period, .. } => {
+            let tick = period.map(|period| tick % period).unwrap_or(tick);
+            // Before the first point, the first point applies
+            points
+                .iter()
+                .take_while(|point| point.tick <= tick)
+                .last()
+                .unwrap_or(&points[0])
+                .factor
+        }
+    }
+}
+
+/// The birth and death rates of every specie, looked up at the position of a cell.
+/// Rates with a schedule are scaled by the schedule's factor in the current tick, see `update`.
+pub struct Rates {
+    size: Size,
+    birth_rates: Vec<Rate>, // Indexed by specie id - 1
+    death_rates: Vec<Rate>, // Indexed by specie id - 1
+    birth_rate_schedules: Vec<Option<ScheduleParams>>,
+    death_rate_schedules: Vec<Option<ScheduleParams>>,
+    birth_rate_factors: Vec<f32>, // Factors of the schedules in the current tick
+    death_rate_factors: Vec<f32>,
+}
+
+impl Rates {
+    /// Returns an error if a rate image cannot be read.
+    pub fn from_params(params: &ModelParams) -> Result<Rates, Box<dyn Error>> {
+        let mut birth_rates = vec![];
+        let mut death_rates = vec![];
+        let mut birth_rate_schedules = vec![];
+        let mut death_rate_schedules = vec![];
+
+        for specie_id in 1..=params.species.len() as u32 {
+            let specie_params = params.get_specie_by_id(specie_id);
+            birth_rates.push(Rate::from_params(&specie_params.birth_rate, params)?);
+            death_rates.push(Rate::from_params(&specie_params.death_rate, params)?);
+            birth_rate_schedules.push(specie_params.birth_rate_schedule.clone());
+            death_rate_schedules.push(specie_params.death_rate_schedule.clone());
+        }
+
+        let mut rates = Rates {
+            size: params.grid_size,
+            birth_rates,
+            death_rates,
+            birth_rate_schedules,
+            death_rate_schedules,
+            birth_rate_factors: vec![],
+            death_rate_factors: vec![],
+        };
+        rates.update(0);
+
+        Ok(rates)
+    }
+
+    /// Evaluates the schedules in the given tick.
+    pub fn update(&mut self, tick: usize) {
+        let factors = |schedules: &Vec<Option<ScheduleParams>>| -> Vec<f32> {
+            schedules
+                .iter()
+                .map(|schedule| {
+                    schedule
+                        .as_ref()
+                        .map(|schedule| schedule_factor(schedule, tick))
+                        .unwrap_or(1.0)
+                })
+                .collect()
+        };
+
+        self.birth_rate_factors = factors(&self.birth_rate_schedules);
+        self.death_rate_factors = factors(&self.death_rate_schedules);
+    }
+
+    /// Returns the birth rate of the given specie in the cell at (x, y) in the current tick.
+    #[inline]
+    pub fn birth_rate(&self, specie_id: u32, x: u32, y: u32) -> f32 {
+        let index = specie_id as usize - 1;
+        let rate = self.birth_rates[index].at(x as usize + y as usize * self.size.w as usize);
+
+        (rate * self.birth_rate_factors[index]).clamp(0.0, 1.0)
+    }
+
+    /// Returns the death rate of the given specie in the cell at (x, y) in the current tick.
+    #[inline]
+    pub fn death_rate(&self, specie_id: u32, x: u32, y: u32) -> f32 {
+        let index = specie_id as usize - 1;
+        let rate = self.death_rates[index].at(x as usize + y as usize * self.size.w as usize);
+
+        (rate * self.death_rate_factors[index]).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::params::{params_from_str, SchedulePoint, ScheduleRange};
+
+    /// Returns the rates of a config with a single specie, Fish, with the given birth rate on a grid of the given size.
+    fn rates_with_birth_rate(birth_rate: &str, w: u32, h: u32, extra_fields: &str) -> Rates {
+        let params = params_from_str(&format!(
+            r#"{{
+                "model": "Simple",
+                "grid_size": {{ "w": {}, "h": {} }},
+                "species": {{
+                    "Fish": {{ "initial_population": 0.1, "death_rate": 0.1, "birth_rate": {} }}
+                }}
+                {}
+            }}"#,
+            w, h, birth_rate, extra_fields
+        ))
+        .unwrap();
+
+        Rates::from_params(&params).unwrap()
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{} is not close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn constant_rate_is_the_same_everywhere() {
+        let rates = rates_with_birth_rate("0.3", 3, 3, "");
+        for y in 0..3 {
+            for x in 0..3 {
+                assert_close(rates.birth_rate(1, x, y), 0.3);
+            }
+        }
+    }
+
+    #[test]
+    fn gradient_interpolates_between_edges() {
+        let rates =
+            rates_with_birth_rate(r#"{ "Gradient": { "from": 0.0, "to": 1.0 } }"#, 5, 2, "");
+
+        // Values are taken at the cell centers, so the edge cells lie half a cell inside the ends
+        assert_close(rates.birth_rate(1, 0, 0), 0.1);
+        assert_close(rates.birth_rate(1, 2, 0), 0.5);
+        assert_close(rates.birth_rate(1, 4, 1), 0.9);
+    }
+
+    #[test]
+    fn vertical_gradient_interpolates_between_north_and_south() {
+        let rates = rates_with_birth_rate(
+            r#"{ "Gradient": { "from": 0.8, "to": 0.2, "vertical": true } }"#,
+            2,
+            3,
+            "",
+        );
+
+        assert_close(rates.birth_rate(1, 1, 0), 0.7);
+        assert_close(rates.birth_rate(1, 0, 1), 0.5);
+        assert_close(rates.birth_rate(1, 1, 2), 0.3);
+    }
+
+    #[test]
+    fn image_is_sampled_per_cell() {
+        // Black, gray and white pixels in a single row, scaled to a grid of 6 by 2 cells
+        let rates = rates_with_birth_rate(
+            r#"{ "Image": {
+                "image": { "width": 3, "height": 1, "pixels": [0, 8421504, 16777215] },
+                "min": 0.2,
+                "max": 0.6
+            } }"#,
+            6,
+            2,
+            "",
+        );
+
+        for &y in &[0, 1] {
+            assert_close(rates.birth_rate(1, 0, y), 0.2);
+            assert_close(rates.birth_rate(1, 1, y), 0.2);
+            assert_close(rates.birth_rate(1, 2, y), 0.2 + 0.4 * 128.0 / 255.0);
+            assert_close(rates.birth_rate(1, 5, y), 0.6);
+        }
+    }
+
+    #[test]
+    fn regions_fall_back_to_default_outside_regions() {
+        let rates = rates_with_birth_rate(
+            r#"{ "Regions": { "default": 0.1, "values": { "East": 0.9, "Corner": 0.5 } } }"#,
+            4,
+            4,
+            r#", "regions": {
+                "East": { "x": 0.5, "y": 0.0, "w": 0.5, "h": 1.0 },
+                "Corner": { "x": 0.5, "y": 0.0, "w": 0.5, "h": 0.5 }
+            }"#,
+        );
+
+        assert_close(rates.birth_rate(1, 0, 0), 0.1);
+        assert_close(rates.birth_rate(1, 1, 3), 0.1);
+        assert_close(rates.birth_rate(1, 3, 3), 0.9);
+        // East comes after Corner alphabetically, so it applies where they overlap
+        assert_close(rates.birth_rate(1, 2, 0), 0.9);
+    }
+
+    fn time_series(points: &[(usize, f32)], period: Option<usize>) -> ScheduleParams {
+        ScheduleParams::TimeSeries {
+            file: None,
+            points: points
+                .iter()
+                .map(|&(tick, factor)| SchedulePoint { tick, factor })
+                .collect(),
+            period,
+        }
+    }
+
+    #[test]
+    fn piecewise_applies_factor_within_ranges() {
+        let schedule = ScheduleParams::Piecewise {
+            ranges: vec![
+                ScheduleRange {
+                    from: 10,
+                    to: 20,
+                    factor: 2.0,
+                },
+                ScheduleRange {
+                    from: 20,
+                    to: 30,
+                    factor: 0.5,
+                },
+            ],
+            period: None,
+        };
+
+        assert_close(schedule_factor(&schedule, 0), 1.0);
+        assert_close(schedule_factor(&schedule, 9), 1.0);
+        // At a breakpoint, the range that starts there applies
+        assert_close(schedule_factor(&schedule, 10), 2.0);
+        assert_close(schedule_factor(&schedule, 15), 2.0);
+        assert_close(schedule_factor(&schedule, 20), 0.5);
+        assert_close(schedule_factor(&schedule, 29), 0.5);
+        assert_close(schedule_factor(&schedule, 30), 1.0);
+        assert_close(schedule_factor(&schedule, 1000), 1.0);
+    }
+
+    #[test]
+    fn piecewise_repeats_every_period() {
+        let schedule = ScheduleParams::Piecewise {
+            ranges: vec![ScheduleRange {
+                from: 0,
+                to: 5,
+                factor: 3.0,
+            }],
+            period: Some(10),
+        };
+
+        assert_close(schedule_factor(&schedule, 4), 3.0);
+        assert_close(schedule_factor(&schedule, 5), 1.0);
+        assert_close(schedule_factor(&schedule, 10), 3.0);
+        assert_close(schedule_factor(&schedule, 27), 1.0);
+    }
+
+    #[test]
+    fn sinusoidal_follows_sine_wave() {
+        let schedule = ScheduleParams::Sinusoidal {
+            period: 100.0,
+            amplitude: 0.5,
+            phase: 0.0,
+        };
+
+        assert_close(schedule_factor(&schedule, 0), 1.0);
+        assert_close(schedule_factor(&schedule, 25), 1.5);
+        assert_close(schedule_factor(&schedule, 50), 1.0);
+        assert_close(schedule_factor(&schedule, 75), 0.5);
+        assert_close(schedule_factor(&schedule, 125), 1.5);
+
+        let shifted = ScheduleParams::Sinusoidal {
+            period: 100.0,
+            amplitude: 0.5,
+            phase: 25.0,
+        };
+        assert_close(schedule_factor(&shifted, 0), 1.5);
+    }
+
+    #[test]
+    fn time_series_holds_each_point_until_the_next() {
+        let schedule = time_series(&[(5, 2.0), (10, 0.5), (20, 1.5)], None);
+
+        // Before the first point, the first point applies
+        assert_close(schedule_factor(&schedule, 0), 2.0);
+        assert_close(schedule_factor(&schedule, 5), 2.0);
+        assert_close(schedule_factor(&schedule, 9), 2.0);
+        assert_close(schedule_factor(&schedule, 10), 0.5);
+        assert_close(schedule_factor(&schedule, 19), 0.5);
+        // After the last point, the last point holds
+        assert_close(schedule_factor(&schedule, 20), 1.5);
+        assert_close(schedule_factor(&schedule, 1000), 1.5);
+    }
+
+    #[test]
+    fn time_series_repeats_every_period() {
+        let schedule = time_series(&[(0, 1.0), (5, 0.25)], Some(10));
+
+        assert_close(schedule_factor(&schedule, 4), 1.0);
+        assert_close(schedule_factor(&schedule, 9), 0.25);
+        assert_close(schedule_factor(&schedule, 10), 1.0);
+        assert_close(schedule_factor(&schedule, 15), 0.25);
+    }
+
+    #[test]
+    fn scheduled_rates_are_capped() {
+        let rates = rates_with_birth_rate(
+            r#"0.6, "birth_rate_schedule": { "Piecewise": { "ranges": [{ "from": 0, "to": 1, "factor": 2.0 }] } }"#,
+            2,
+            2,
+            "",
+        );
+        assert_close(rates.birth_rate(1, 0, 0), 1.0);
+    }
+}
@@ -6,79 +6,123 @@ pub struct SimpleModel {
     grid: Grid,
     params: ModelParams,
     rng: PRng,
+    seed: u64, // Seed that the random number generator was created with
+    neighborhood: Neighborhood,
+    rates: Rates,
+    ticks_elapsed: usize, // Number of ticks since the model was created, used to evaluate the rate schedules
 }
 
 impl SimpleModel {
-    pub fn new(params: ModelParams) -> SimpleModel {
-        let grid = Grid::new(params.grid_size);
-        let rng = PRng::seed_from_u64(params.random_seed.unwrap_or(time_ns() as u64));
-
-        SimpleModel { grid, params, rng }
+    pub fn new(params: ModelParams) -> Result<SimpleModel, Box<dyn Error>> {
+        let grid = Grid::from_params(&params)?;
+        let neighborhood = Neighborhood::new(
+            params.topology,
+            params
+                .neighborhoods
+                .interaction
+                .as_ref()
+                .unwrap_or(&NeighborhoodParams::moore(1)),
+        );
+        let seed = params.random_seed.unwrap_or(time_ns() as u64);
+        let rng = PRng::seed_from_u64(seed);
+        let rates = Rates::from_params(&params)?;
+
+        Ok(SimpleModel {
+            grid,
+            params,
+            rng,
+            seed,
+            neighborhood,
+            rates,
+            ticks_elapsed: 0,
+        })
     }
 
-    /// Determines the next state of the given cell, given the current state and the cell's surrounding neighbors.
-    fn next_cell_state(&mut self, cell: &Cell, neighbors: &Vec<Cell>) -> Cell {
+    /// Determines the next state of the cell at (x, y), given the current state and the cell's surrounding neighbors.
+    fn next_cell_state(
+        &self,
+        x: u32,
+        y: u32,
+        cell: &Cell,
+        neighbors: &[Cell],
+        rng: &mut PRng,
+    ) -> Cell {
         let (n_predators, dominant_predator_id) =
-            get_neighbor_predators(cell, neighbors, &self.params, &mut self.rng);
+            get_neighbor_predators(cell, neighbors, &self.params, rng);
 
-        match cell {
-            &Cell::Animal(specie_id) => {
+        match *cell {
+            Cell::Animal(specie_id, state) => {
                 let specie_params = self.params.get_specie_by_id(specie_id);
-                let cell_birth_rate = specie_params.birth_rate;
-                let cell_death_rate = specie_params.death_rate;
+                let cell_death_rate = self.rates.death_rate(specie_id, x, y);
+                let specie_uses_energy = specie_params.uses_energy();
                 let specie_is_herbivore = self.params.is_specie_herbivore(specie_id);
 
                 if specie_is_herbivore || n_predators > 0 {
                     // Cell is prey
-                    let random_1 = self.rng.gen::<f32>();
+                    let random_1 = rng.gen::<f32>();
 
                     if random_1 < (1.0f32 - cell_death_rate).powf(n_predators as f32) {
                         // Hunt failed.
                     } else {
-                        let predator_birth_rate = self
-                            .params
-                            .get_specie_by_id(dominant_predator_id)
-                            .birth_rate;
-                        let random_2 = self.rng.gen::<f32>();
+                        let predator_birth_rate = self.rates.birth_rate(dominant_predator_id, x, y);
+                        let random_2 = rng.gen::<f32>();
                         if random_2 < predator_birth_rate {
                             // Cell becomes predator by breeding
-                            return Cell::Animal(dominant_predator_id);
+                            return newborn_animal(dominant_predator_id, &self.params);
                         }
                     }
                 }
                 if !specie_is_herbivore {
                     // Cell is a predator
-                    let random = self.rng.gen::<f32>();
-                    if random < cell_death_rate {
-                        // Cell becomes empty due to predator death
-                        Cell::Empty
+                    let has_eaten = if specie_uses_energy {
+                        // Predator feeds if it succeeds in hunting one of its neighboring prey
+                        let (n_prey, dominant_prey_id) =
+                            get_neighbor_prey(cell, neighbors, &self.params, rng);
+                        let prey_death_rate = if n_prey == 0 {
+                            0.0
+                        } else {
+                            self.rates.death_rate(dominant_prey_id, x, y)
+                        };
+                        let random = rng.gen::<f32>();
+                        random >= (1.0f32 - prey_death_rate).powf(n_prey as f32)
                     } else {
+                        let random = rng.gen::<f32>();
+                        if random < cell_death_rate {
+                            // Cell becomes empty due to predator death
+                            return Cell::Empty;
+                        }
+                        false
+                    };
+
+                    match live_one_step(specie_id, state, has_eaten, &self.params) {
                         // Cell remains predator
-                        Cell::Animal(specie_id)
+                        Some(state) => Cell::Animal(specie_id, state),
+                        // Cell becomes empty due to starvation or old age
+                        None => Cell::Empty,
                     }
                 } else {
-                    // Cell is a herbivore, remains the same.
-                    Cell::Animal(specie_id)
+                    // Cell is a herbivore, remains the same unless it dies of old age.
+                    match live_one_step(specie_id, state, true, &self.params) {
+                        Some(state) => Cell::Animal(specie_id, state),
+                        None => Cell::Empty,
+                    }
                 }
             }
-            &Cell::Empty => {
+            Cell::Empty => {
                 let (n_same_herbivores, dominant_herbivore_id) =
-                    get_neighbor_herbivores(neighbors, &self.params, &mut self.rng);
+                    get_neighbor_herbivores(neighbors, &self.params, rng);
 
                 if n_same_herbivores == 0 || n_predators > 0 {
                     // Cell remains empty
                     Cell::Empty
                 } else {
                     // Cell may become the neighborhood's most common herbivore by breeding
-                    let random = self.rng.gen::<f32>();
-                    let cell_birth_rate = self
-                        .params
-                        .get_specie_by_id(dominant_herbivore_id)
-                        .birth_rate;
+                    let random = rng.gen::<f32>();
+                    let cell_birth_rate = self.rates.birth_rate(dominant_herbivore_id, x, y);
 
                     if random < (1.0f32 - cell_birth_rate).powf(n_same_herbivores as f32) {
                         // Cell becomes herbivore by breeding
-                        Cell::Animal(dominant_herbivore_id)
+                        newborn_animal(dominant_herbivore_id, &self.params)
                     } else {
                         Cell::Empty
                     }
@@ -89,41 +133,66 @@ impl SimpleModel {
 }
 
 impl Model for SimpleModel {
-    fn populate(&mut self) {
-        self.grid.populate(&self.params, &mut self.rng);
+    fn populate(&mut self) -> Result<(), Box<dyn Error>> {
+        self.grid.populate(&self.params, &mut self.rng)
     }
 
     fn tick(&mut self) {
-        let grid_size = self.grid.get_size();
-        let mut new_cells = Grid::new(grid_size);
-
-        for x in 0..grid_size.w {
-            for y in 0..grid_size.h {
-                let cell = self.grid.get_cell_at(x, y).clone();
-                let mut neighbors: Vec<Cell> = vec![];
-
-                {
-                    neighbors = self
-                        .grid
-                        .moore_neighborhood(x, y, 1, None)
-                        .iter()
-                        .map(|cell| (*cell).clone())
-                        .collect();
-                }
-
-                let new_cell = self.next_cell_state(&cell, &neighbors);
-                new_cells.set_cell_at(x, y, new_cell);
-            }
-        }
-
-        self.grid = new_cells;
+        self.rates.update(self.ticks_elapsed);
+        let tick_seed = self.rng.gen::<u64>();
+        let model = &*self;
+
+        let new_cells = map_cells(
+            self.grid.get_size(),
+            tick_seed,
+            thread_count(self.params.threads),
+            |x, y, rng| {
+                let cell = model.grid.get_cell_at(x, y).clone();
+                let neighbors = model.grid.neighborhood(x, y, &model.neighborhood, None);
+
+                model.next_cell_state(x, y, &cell, &neighbors, rng)
+            },
+        );
+
+        self.grid = self.grid.with_cells(new_cells);
+        self.ticks_elapsed += 1;
     }
 
     fn get_grid(&self) -> &Grid {
         &self.grid
     }
 
+    fn get_grid_mut(&mut self) -> &mut Grid {
+        &mut self.grid
+    }
+
     fn get_params(&self) -> &ModelParams {
         &self.params
     }
+
+    fn get_rng(&self) -> &PRng {
+        &self.rng
+    }
+
+    fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn inspect_cell(&self, x: u32, y: u32) -> Vec<String> {
+        let neighbors = self.grid.neighborhood(x, y, &self.neighborhood, None);
+
+        describe_cell(
+            &self.grid,
+            &self.params,
+            x,
+            y,
+            &[("Interaction", &neighbors)],
+        )
+    }
+
+    fn restore(&mut self, grid: Grid, rng: PRng, ticks_elapsed: usize) {
+        self.grid = grid;
+        self.rng = rng;
+        self.ticks_elapsed = ticks_elapsed;
+    }
 }
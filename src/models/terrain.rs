@@ -0,0 +1,125 @@
+use crate::models::params::ModelParams;
+use crate::util::Size;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+/// A habitat with the species resolved to specie ids.
+#[derive(Clone, Serialize, Deserialize)]
+struct Habitat {
+    obstacle: bool,
+    allowed_species: Option<Vec<u32>>, // All species are allowed if None
+}
+
+/// The habitat of every cell of the grid. Open terrain (habitat id 0) can be occupied by any specie.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Terrain {
+    habitats: Vec<Habitat>,
+    habitat_ids: Vec<u32>, // Habitat id per cell, in row-major order
+    has_obstacles: bool,
+}
+
+impl Terrain {
+    /// Returns terrain that is open everywhere.
+    pub fn open(size: Size) -> Terrain {
+        Terrain {
+            habitats: vec![],
+            habitat_ids: vec![0; size.w as usize * size.h as usize],
+            has_obstacles: false,
+        }
+    }
+
+    /// Lays out the habitats from the terrain map and initial image in the parameters, scaled to the grid size.
+    /// Returns an error if the initial image cannot be read.
+    pub fn from_params(params: &ModelParams) -> Result<Terrain, Box<dyn Error>> {
+        let size = params.grid_size;
+        let mut terrain = Terrain::open(size);
+
+        terrain.habitats = params
+            .habitats
+            .values()
+            .map(|habitat_params| Habitat {
+                obstacle: habitat_params.obstacle,
+                allowed_species: habitat_params.species.as_ref().map(|specie_names| {
+                    specie_names
+                        .iter()
+                        .map(|specie_name| params.specie_id_from_name(specie_name))
+                        .collect()
+                }),
+            })
+            .collect();
+
+        if let Some(terrain_params) = &params.terrain {
+            if let Some(rows) = &terrain_params.map {
+                let map: Vec<Vec<char>> = rows.iter().map(|row| row.chars().collect()).collect();
+                let map_height = map.len();
+                let map_width = map[0].len();
+
+                for y in 0..size.h {
+                    for x in 0..size.w {
+                        let map_x = x as usize * map_width / size.w as usize;
+                        let map_y = y as usize * map_height / size.h as usize;
+
+                        if let Some(habitat_name) = terrain_params.legend.get(&map[map_y][map_x]) {
+                            terrain.habitat_ids[x as usize + y as usize * size.w as usize] =
+                                params.habitat_id_from_name(habitat_name);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Pixels in the initial image with the color of a habitat override the terrain map
+        if let Some(image) = params.get_initial_image()? {
+            let habitat_colors = params.habitat_colors();
+
+            for y in 0..size.h {
+                for x in 0..size.w {
+                    if let Some(habitat_id) = habitat_colors.get(&image.color_at_cell(x, y, size)) {
+                        terrain.habitat_ids[x as usize + y as usize * size.w as usize] =
+                            *habitat_id;
+                    }
+                }
+            }
+        }
+
+        terrain.has_obstacles =
+            (0..terrain.habitat_ids.len()).any(|index| terrain.is_obstacle(index));
+
+        Ok(terrain)
+    }
+
+    /// Returns the habitat id of the cell with the given index, 0 for open terrain.
+    #[inline]
+    pub fn get_habitat_id(&self, index: usize) -> u32 {
+        self.habitat_ids[index]
+    }
+
+    #[inline]
+    pub fn is_obstacle(&self, index: usize) -> bool {
+        match self.habitat_ids[index] {
+            0 => false,
+            habitat_id => self.habitats[habitat_id as usize - 1].obstacle,
+        }
+    }
+
+    /// Returns whether an animal of the given specie may occupy the cell with the given index.
+    #[inline]
+    pub fn allows(&self, index: usize, specie_id: u32) -> bool {
+        match self.habitat_ids[index] {
+            0 => true,
+            habitat_id => {
+                let habitat = &self.habitats[habitat_id as usize - 1];
+                !habitat.obstacle
+                    && habitat
+                        .allowed_species
+                        .as_ref()
+                        .map(|allowed_species| allowed_species.contains(&specie_id))
+                        .unwrap_or(true)
+            }
+        }
+    }
+
+    pub const fn has_obstacles(&self) -> bool {
+        self.has_obstacles
+    }
+}
@@ -1,11 +1,74 @@
-use crate::models::{Grid, ModelParams};
+use crate::models::{Grid, Model, ModelParams};
+use std::collections::{BTreeMap, VecDeque};
 use std::fs::File;
 use std::io::*;
 
+/// Counts the number of animals of every specie in the grid, by specie name.
+pub fn population_counts(grid: &Grid, params: &ModelParams) -> BTreeMap<String, usize> {
+    let cell_specie_ids = grid.get_cell_specie_ids();
+
+    params
+        .species
+        .keys()
+        .map(|specie_name| {
+            let specie_id = params.specie_id_from_name(specie_name);
+            let specie_count = cell_specie_ids
+                .iter()
+                .filter(|id| **id == specie_id)
+                .count();
+
+            (specie_name.clone(), specie_count)
+        })
+        .collect()
+}
+
+/// Population counts of the last ticks, e.g. to plot the population over time.
+pub struct PopulationHistory {
+    length: usize,                // Maximum number of ticks to keep
+    counts: VecDeque<Vec<usize>>, // Count of every specie (in order of specie id) per tick, oldest first
+}
+
+impl PopulationHistory {
+    pub fn new(length: usize) -> PopulationHistory {
+        PopulationHistory {
+            length,
+            counts: VecDeque::with_capacity(length),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.counts.clear();
+    }
+
+    /// Adds the current population counts of the model, dropping the oldest counts if the history is full.
+    pub fn record(&mut self, model: &dyn Model) {
+        if self.counts.len() == self.length {
+            self.counts.pop_front();
+        }
+
+        self.counts.push_back(
+            population_counts(model.get_grid(), model.get_params())
+                .values()
+                .cloned()
+                .collect(),
+        );
+    }
+
+    pub fn get_counts(&self) -> &VecDeque<Vec<usize>> {
+        &self.counts
+    }
+
+    pub const fn get_length(&self) -> usize {
+        self.length
+    }
+}
+
 // Statistics writer. Collects statistics from a model and writes them to a CSV file.
+// Metadata of the run is written to a log file next to it, whose path is the CSV file's path with `.log` appended.
 pub struct Stats {
     file_path: String,
     file_handle: Option<File>,
+    log_handle: Option<File>,
 }
 
 impl Stats {
@@ -13,59 +76,186 @@ impl Stats {
         Stats {
             file_path: file_path.to_string(),
             file_handle: None,
+            log_handle: None,
         }
     }
 
     pub fn reset(&mut self) {
         self.file_handle = None;
+        self.log_handle = None;
     }
 
-    pub fn collect(
-        &mut self,
-        ticks_elapsed: usize,
-        grid: &Grid,
-        params: &ModelParams,
-    ) -> Result<()> {
+    /// Continues the statistics of a model that was loaded from a checkpoint. Rows that have already been written are kept,
+    /// and a line in the log file marks where the rows of the loaded model start.
+    pub fn resume(&mut self, ticks_elapsed: usize, model: &dyn Model) -> Result<()> {
+        match &mut self.log_handle {
+            Some(log_handle) => writeln!(
+                log_handle,
+                "Checkpoint loaded at {} (seed {})",
+                ticks_elapsed,
+                model.get_seed()
+            ),
+            // Nothing has been written yet, the file is created when the first row is written
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the handle to the CSV file. The file (and its header) and the log file are created
+    /// on the first call after creation or a reset.
+    fn file_handle(&mut self, model: &dyn Model) -> Result<&mut File> {
         if self.file_handle.is_none() {
-            let mut file_handle = std::fs::File::create(&self.file_path)?;
+            let mut log_handle = std::fs::File::create(format!("{}.log", self.file_path))?;
 
+            // Write the seed to the log and print it along with the stats file, so the run can be reproduced
+            writeln!(log_handle, "Seed: {}", model.get_seed())?;
+            println!(
+                "writing statistics to {} (random seed: {})",
+                self.file_path,
+                model.get_seed()
+            );
+
+            self.log_handle = Some(log_handle);
+
+            let mut file_handle = std::fs::File::create(&self.file_path)?;
             let mut line = String::from("Time");
 
-            for specie_name in params.species.keys() {
-                line.push_str(",");
+            for specie_name in model.get_params().species.keys() {
+                line.push(',');
                 line.push_str(specie_name);
             }
 
             // Construct CSV header
-            writeln!(file_handle, "{}", line);
+            writeln!(file_handle, "{}", line)?;
 
             self.file_handle = Some(file_handle);
         }
 
-        match &mut self.file_handle {
-            Some(file_handle) => {
-                let mut line = String::from(ticks_elapsed.to_string());
+        Ok(self.file_handle.as_mut().unwrap())
+    }
 
-                let cell_specie_ids = grid.get_cell_specie_ids();
+    /// Returns the handle to the log file, which is created together with the CSV file.
+    fn log_handle(&mut self, model: &dyn Model) -> Result<&mut File> {
+        self.file_handle(model)?;
 
-                for specie_name in params.species.keys() {
-                    let specie_id = params.specie_id_from_name(specie_name);
+        Ok(self.log_handle.as_mut().unwrap())
+    }
 
-                    let specie_count = cell_specie_ids
-                        .iter()
-                        .filter(|id| **id == specie_id)
-                        .count();
+    /// Writes the population of every specie at the given time to the CSV file.
+    pub fn collect(&mut self, ticks_elapsed: usize, model: &dyn Model) -> Result<()> {
+        let mut line = ticks_elapsed.to_string();
 
-                    line.push_str(",");
-                    line.push_str(&specie_count.to_string());
-                }
+        for specie_count in population_counts(model.get_grid(), model.get_params()).values() {
+            line.push(',');
+            line.push_str(&specie_count.to_string());
+        }
 
-                // Write CSV row
-                writeln!(file_handle, "{}", line);
+        // Write CSV row
+        writeln!(self.file_handle(model)?, "{}", line)
+    }
 
-                Ok(())
-            }
-            None => Ok(()),
+    /// Writes an event that took place at the given time to the log file.
+    pub fn log_event(
+        &mut self,
+        ticks_elapsed: usize,
+        model: &dyn Model,
+        description: &str,
+    ) -> Result<()> {
+        writeln!(
+            self.log_handle(model)?,
+            "Event at {}: {}",
+            ticks_elapsed,
+            description
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::create_model_from_str;
+    use std::fs;
+
+    const CONFIG: &str = r#"{
+        "model": "Simple",
+        "random_seed": 7,
+        "grid_size": { "w": 8, "h": 8 },
+        "species": {
+            "Fish": { "initial_population": 0.2, "death_rate": 0.1, "birth_rate": 0.5 }
+        }
+    }"#;
+
+    /// Returns a path in the temporary directory that is unique to the given test.
+    fn temp_path(test_name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("onderzoeksmethoden-test-{}.csv", test_name));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn writes_events_to_the_log() {
+        let file_path = temp_path("stats-events");
+        let model = create_model_from_str(CONFIG).unwrap();
+        let mut stats = Stats::new(&file_path);
+
+        stats.collect(0, model.as_ref()).unwrap();
+        stats.log_event(1, model.as_ref(), "Culled 3 Fish").unwrap();
+        stats.collect(1, model.as_ref()).unwrap();
+        drop(stats);
+
+        let csv = fs::read_to_string(&file_path).unwrap();
+        let log = fs::read_to_string(format!("{}.log", file_path)).unwrap();
+        fs::remove_file(&file_path).unwrap();
+        fs::remove_file(format!("{}.log", file_path)).unwrap();
+
+        assert_eq!(csv.lines().count(), 3);
+        assert!(!csv.contains("Culled"));
+        assert_eq!(log, "Seed: 7\nEvent at 1: Culled 3 Fish\n");
+    }
+
+    #[test]
+    fn writes_loaded_checkpoints_to_the_log() {
+        let file_path = temp_path("stats-resume");
+        let model = create_model_from_str(CONFIG).unwrap();
+        let mut stats = Stats::new(&file_path);
+
+        // Nothing is written before the first row
+        stats.resume(5, model.as_ref()).unwrap();
+        stats.collect(5, model.as_ref()).unwrap();
+        stats.resume(2, model.as_ref()).unwrap();
+        stats.collect(2, model.as_ref()).unwrap();
+        drop(stats);
+
+        let csv = fs::read_to_string(&file_path).unwrap();
+        let log = fs::read_to_string(format!("{}.log", file_path)).unwrap();
+        fs::remove_file(&file_path).unwrap();
+        fs::remove_file(format!("{}.log", file_path)).unwrap();
+
+        assert_eq!(csv.lines().count(), 3);
+        assert_eq!(log, "Seed: 7\nCheckpoint loaded at 2 (seed 7)\n");
+    }
+
+    #[test]
+    fn writes_the_seed_to_the_log_and_only_rows_to_the_csv_file() {
+        let file_path = temp_path("stats-seed");
+        let mut model = create_model_from_str(CONFIG).unwrap();
+        let mut stats = Stats::new(&file_path);
+
+        for ticks_elapsed in 0..3 {
+            stats.collect(ticks_elapsed, model.as_ref()).unwrap();
+            model.tick();
         }
+        drop(stats);
+
+        let csv = fs::read_to_string(&file_path).unwrap();
+        let log = fs::read_to_string(format!("{}.log", file_path)).unwrap();
+        fs::remove_file(&file_path).unwrap();
+        fs::remove_file(format!("{}.log", file_path)).unwrap();
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "Time,Fish");
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1..]
+            .iter()
+            .all(|line| line.split(',').all(|value| value.parse::<usize>().is_ok())));
+        assert_eq!(log, "Seed: 7\n");
     }
 }
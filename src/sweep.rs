@@ -0,0 +1,352 @@
+use crate::headless::run_params;
+use crate::models::params::{load_files, params_from_file_with_seed, validate_params};
+use crate::models::ModelParams;
+use serde::Deserialize;
+use serde_json::{Number, Value};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Values that a swept parameter takes, either an inclusive range or an explicit list.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SweepValues {
+    Range {
+        from: Number,
+        to: Number,
+        step: Number,
+    },
+    List(Vec<Value>),
+}
+
+/// Describes a parameter sweep, loaded from a JSON file.
+#[derive(Deserialize)]
+struct SweepParams {
+    ticks: usize,            // Number of ticks per run
+    output_dir: String, // Directory to write the statistics of each run and the index to, relative to the sweep file
+    seeds: Option<Vec<u64>>, // (optional) Random seeds, every combination is run once per seed
+    // Mapping from parameter path (e.g. species.Tuna.death_rate) -> values
+    parameters: BTreeMap<String, SweepValues>,
+}
+
+fn sweep_error(message: String) -> Box<dyn Error> {
+    Box::new(io::Error::other(message))
+}
+
+impl SweepValues {
+    fn values(&self) -> Result<Vec<Value>, Box<dyn Error>> {
+        match self {
+            SweepValues::List(values) => Ok(values.clone()),
+            SweepValues::Range { from, to, step } => {
+                let (from_f, to_f, step_f) = (
+                    from.as_f64().unwrap(),
+                    to.as_f64().unwrap(),
+                    step.as_f64().unwrap(),
+                );
+                if step_f <= 0.0 || to_f < from_f {
+                    return Err(sweep_error(format!(
+                        "Invalid range from {} to {} with step {}",
+                        from, to, step
+                    )));
+                }
+
+                // Integer ranges produce integer values, so they can be used for integer fields.
+                let is_integer = from.is_i64() && to.is_i64() && step.is_i64();
+                let n_steps = ((to_f - from_f) / step_f + 1e-9).floor() as usize;
+
+                Ok((0..(n_steps + 1))
+                    .map(|i| {
+                        if is_integer {
+                            Value::from(from.as_i64().unwrap() + i as i64 * step.as_i64().unwrap())
+                        } else {
+                            // Round to remove floating point accumulation errors, e.g. 0.30000000000000004
+                            let value = ((from_f + i as f64 * step_f) * 1e9).round() / 1e9;
+                            Value::from(value)
+                        }
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Sets the field at the given dot-separated path in a JSON value.
+fn set_value_at_path(root: &mut Value, path: &str, value: Value) -> Result<(), Box<dyn Error>> {
+    let mut current = root;
+    for key in path.split('.') {
+        current = match current {
+            Value::Object(map) => match map.get_mut(key) {
+                Some(child) => child,
+                None => return Err(sweep_error(format!("Parameter {} does not exist", path))),
+            },
+            _ => return Err(sweep_error(format!("Parameter {} does not exist", path))),
+        };
+    }
+    *current = value;
+
+    Ok(())
+}
+
+/// Formats a JSON value as a CSV field, quoting it if necessary.
+fn csv_field(value: &Value) -> String {
+    let field = match value {
+        Value::String(string) => string.clone(),
+        _ => value.to_string(),
+    };
+
+    if field.contains(',') || field.contains('"') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// Returns every combination of the given value lists (the cartesian product).
+fn combinations(value_lists: &Vec<Vec<Value>>) -> Vec<Vec<Value>> {
+    let mut combinations = vec![vec![]];
+    for values in value_lists {
+        let mut new_combinations = vec![];
+        for combination in combinations.iter() {
+            for value in values {
+                let mut new_combination = combination.clone();
+                new_combination.push(value.clone());
+                new_combinations.push(new_combination);
+            }
+        }
+        combinations = new_combinations;
+    }
+
+    combinations
+}
+
+/// Runs every combination of the swept parameters on top of the base config, optionally with several seeds each.
+/// Writes the statistics of each run to a separate file, and an index that links run IDs to parameter values.
+/// The random seed overrides the seed in the base config, but not the seeds in the sweep file.
+pub fn run(
+    sweep_path: &str,
+    config_path: &str,
+    random_seed: Option<u64>,
+) -> Result<(), Box<dyn Error>> {
+    let sweep_params: SweepParams = serde_json::from_str(&fs::read_to_string(sweep_path)?)?;
+    let base_params = serde_json::to_value(params_from_file_with_seed(config_path, random_seed)?)?;
+
+    let parameter_paths: Vec<&String> = sweep_params.parameters.keys().collect();
+    let mut value_lists = vec![];
+    for values in sweep_params.parameters.values() {
+        value_lists.push(values.values()?);
+    }
+    let seeds: Vec<Option<u64>> = match &sweep_params.seeds {
+        Some(seeds) => seeds.iter().map(|seed| Some(*seed)).collect(),
+        None => vec![None],
+    };
+
+    let combinations = combinations(&value_lists);
+    let n_runs = combinations.len() * seeds.len();
+
+    // Like the paths in config files, the output directory is relative to the file that names it
+    let sweep_dir = Path::new(sweep_path).parent().unwrap_or(Path::new(""));
+    let output_dir = sweep_dir.join(&sweep_params.output_dir);
+    fs::create_dir_all(&output_dir)?;
+    let mut index_file = File::create(output_dir.join("index.csv"))?;
+
+    let mut line = String::from("Run,Seed");
+    for parameter_path in parameter_paths.iter() {
+        line.push(',');
+        line.push_str(parameter_path);
+    }
+    line.push_str(",Stats file,Error");
+    writeln!(index_file, "{}", line)?;
+
+    // A failed run is recorded in the index and does not stop the remaining runs
+    let mut n_failed = 0;
+    let mut run_id = 0;
+    for combination in combinations.iter() {
+        for seed in seeds.iter() {
+            run_id += 1;
+
+            let stats_file_name = format!("run-{}.csv", run_id);
+            let stats_path = output_dir.join(&stats_file_name);
+
+            println!("run {}/{}", run_id, n_runs);
+            let result = run_combination(
+                &base_params,
+                sweep_dir,
+                &parameter_paths,
+                combination,
+                *seed,
+                stats_path.to_str(),
+                sweep_params.ticks,
+            );
+
+            let mut line = format!("{},", run_id);
+            match &result {
+                Ok(used_seed) => line.push_str(&used_seed.to_string()),
+                Err(_) => line.push_str(&seed.map_or(String::new(), |seed| seed.to_string())),
+            }
+            for value in combination.iter() {
+                line.push(',');
+                line.push_str(&csv_field(value));
+            }
+            line.push(',');
+            match &result {
+                Ok(_) => {
+                    line.push_str(&stats_file_name);
+                    line.push(',');
+                }
+                Err(error) => {
+                    println!("run {} failed: {}", run_id, error);
+                    n_failed += 1;
+                    line.push(',');
+                    line.push_str(&csv_field(&Value::from(error.to_string())));
+                }
+            }
+            writeln!(index_file, "{}", line)?;
+        }
+    }
+
+    if n_failed > 0 {
+        return Err(sweep_error(format!(
+            "{} of {} runs failed, see {}",
+            n_failed,
+            n_runs,
+            output_dir.join("index.csv").display()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Runs one combination of parameter values on top of the base config, returning the random seed that was used.
+/// Files named by the swept values are relative to the directory of the sweep file.
+fn run_combination(
+    base_params: &Value,
+    sweep_dir: &Path,
+    parameter_paths: &[&String],
+    combination: &[Value],
+    seed: Option<u64>,
+    stats_path: Option<&str>,
+    ticks: usize,
+) -> Result<u64, Box<dyn Error>> {
+    let mut params_value = base_params.clone();
+    for (parameter_path, value) in parameter_paths.iter().zip(combination.iter()) {
+        set_value_at_path(&mut params_value, parameter_path, value.clone())?;
+    }
+
+    // The files of the base config have already been read, so this only reads the files named by the swept values
+    let mut params: ModelParams = serde_json::from_value(params_value)?;
+    load_files(&mut params, Some(sweep_dir))?;
+    validate_params(&params)?;
+    if seed.is_some() {
+        params.random_seed = seed;
+    }
+
+    run_params(params, stats_path, ticks, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range_values(json: &str) -> Result<Vec<Value>, Box<dyn Error>> {
+        let values: SweepValues = serde_json::from_str(json).unwrap();
+        values.values()
+    }
+
+    #[test]
+    fn integer_range_includes_both_ends() {
+        let values = range_values(r#"{ "from": 2, "to": 10, "step": 4 }"#).unwrap();
+        assert_eq!(
+            values,
+            vec![Value::from(2), Value::from(6), Value::from(10)]
+        );
+        assert!(values.iter().all(Value::is_i64));
+    }
+
+    #[test]
+    fn range_stops_before_end_not_reached_by_step() {
+        let values = range_values(r#"{ "from": 1, "to": 8, "step": 3 }"#).unwrap();
+        assert_eq!(values, vec![Value::from(1), Value::from(4), Value::from(7)]);
+    }
+
+    #[test]
+    fn range_with_equal_ends_has_one_value() {
+        let values = range_values(r#"{ "from": 0.5, "to": 0.5, "step": 0.1 }"#).unwrap();
+        assert_eq!(values, vec![Value::from(0.5)]);
+    }
+
+    #[test]
+    fn float_range_includes_end_despite_rounding_errors() {
+        // (0.3 - 0.1) / 0.1 is slightly below 2 in floating point
+        let values = range_values(r#"{ "from": 0.1, "to": 0.3, "step": 0.1 }"#).unwrap();
+        assert_eq!(
+            values,
+            vec![Value::from(0.1), Value::from(0.2), Value::from(0.3)]
+        );
+
+        let values = range_values(r#"{ "from": 0.0, "to": 1.0, "step": 0.1 }"#).unwrap();
+        assert_eq!(values.len(), 11);
+        assert_eq!(values[3], Value::from(0.3));
+        assert_eq!(values[10], Value::from(1.0));
+    }
+
+    #[test]
+    fn mixed_range_produces_floats() {
+        let values = range_values(r#"{ "from": 0, "to": 1, "step": 0.5 }"#).unwrap();
+        assert_eq!(
+            values,
+            vec![Value::from(0.0), Value::from(0.5), Value::from(1.0)]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        assert!(range_values(r#"{ "from": 0, "to": 1, "step": 0 }"#).is_err());
+        assert!(range_values(r#"{ "from": 0, "to": 1, "step": -1 }"#).is_err());
+        assert!(range_values(r#"{ "from": 1, "to": 0, "step": 1 }"#).is_err());
+    }
+
+    #[test]
+    fn list_is_used_as_is() {
+        let values = range_values(r#"[3, "a", 0.5]"#).unwrap();
+        assert_eq!(
+            values,
+            vec![Value::from(3), Value::from("a"), Value::from(0.5)]
+        );
+    }
+
+    #[test]
+    fn swept_file_paths_are_read() {
+        let base_params = serde_json::to_value(
+            params_from_file_with_seed("sample-configs/pppe-invasion.json", Some(1)).unwrap(),
+        )
+        .unwrap();
+        let parameter_path = "initial_image".to_string();
+
+        let result = run_combination(
+            &base_params,
+            Path::new("sample-configs"),
+            &[&parameter_path],
+            &[Value::from("missing.png")],
+            None,
+            None,
+            1,
+        );
+        let error = result.err().unwrap().to_string();
+        assert!(error.contains("Failed to read initial image"), "{}", error);
+        assert!(error.contains("missing.png"), "{}", error);
+
+        let seed = run_combination(
+            &base_params,
+            Path::new("sample-configs"),
+            &[&parameter_path],
+            &[Value::from("invasion.png")],
+            None,
+            None,
+            1,
+        )
+        .unwrap();
+        assert_eq!(seed, 1);
+    }
+}
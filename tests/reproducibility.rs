@@ -0,0 +1,84 @@
+use onderzoeksmethoden::checkpoint::{load_checkpoint, save_checkpoint};
+use onderzoeksmethoden::models::events::apply_events;
+use onderzoeksmethoden::models::params::params_from_file_with_seed;
+use onderzoeksmethoden::models::{create_model_from_str, Model};
+use onderzoeksmethoden::util::Size;
+use std::fs;
+
+const CONFIGS: [&str; 12] = [
+    "custom-fish.json",
+    "dsam-fish.json",
+    "pppe-coast.json",
+    "pppe-fish.json",
+    "pppe-fishing.json",
+    "pppe-gradient.json",
+    "pppe-hex.json",
+    "pppe-invasion.json",
+    "pppe-patches.json",
+    "pppe-reef.json",
+    "pppe-seasons.json",
+    "simple-fish.json",
+];
+const SEED: u64 = 42;
+// Smaller than the sample configs' grids, to keep the tests fast in debug builds.
+const GRID_SIZE: Size = Size::new(24, 24);
+// Long enough for the first event in pppe-fishing.json (at tick 50) to take place after resuming.
+const TICKS: usize = 30;
+
+/// Creates and populates a model from a sample config.
+fn create_model(config_name: &str) -> Box<dyn Model> {
+    let config_path = format!("sample-configs/{}", config_name);
+    let mut params = params_from_file_with_seed(&config_path, Some(SEED)).unwrap();
+    params.grid_size = GRID_SIZE;
+
+    // The loaded parameters no longer refer to files relative to the config, so they can be created from a string
+    create_model_from_str(&serde_json::to_string(&params).unwrap()).unwrap()
+}
+
+/// Runs the given number of ticks on a model that has already run for `start_ticks` ticks, like a headless run.
+fn run_ticks(model: &mut Box<dyn Model>, start_ticks: usize, ticks: usize) {
+    for ticks_elapsed in (start_ticks + 1)..=(start_ticks + ticks) {
+        model.tick();
+        apply_events(model, ticks_elapsed);
+    }
+}
+
+fn grid_json(model: &dyn Model) -> String {
+    serde_json::to_string(model.get_grid()).unwrap()
+}
+
+fn rng_json(model: &dyn Model) -> String {
+    serde_json::to_string(model.get_rng()).unwrap()
+}
+
+#[test]
+fn resumed_run_matches_uninterrupted_run() {
+    for config_name in CONFIGS.iter() {
+        let mut uninterrupted = create_model(config_name);
+        run_ticks(&mut uninterrupted, 0, 2 * TICKS);
+
+        let mut interrupted = create_model(config_name);
+        run_ticks(&mut interrupted, 0, TICKS);
+        let checkpoint_path = std::env::temp_dir().join(format!(
+            "onderzoeksmethoden-test-checkpoint-{}",
+            config_name
+        ));
+        let checkpoint_path = checkpoint_path.to_str().unwrap();
+        save_checkpoint(checkpoint_path, interrupted.as_ref(), TICKS).unwrap();
+        let (mut resumed, ticks_elapsed) = load_checkpoint(checkpoint_path).unwrap();
+        fs::remove_file(checkpoint_path).unwrap();
+        assert_eq!(ticks_elapsed, TICKS);
+        run_ticks(&mut resumed, ticks_elapsed, TICKS);
+
+        assert!(
+            grid_json(uninterrupted.as_ref()) == grid_json(resumed.as_ref()),
+            "grids differ between the uninterrupted and the resumed run for {}",
+            config_name
+        );
+        assert!(
+            rng_json(uninterrupted.as_ref()) == rng_json(resumed.as_ref()),
+            "random number generator states differ between the uninterrupted and the resumed run for {}",
+            config_name
+        );
+    }
+}